@@ -10,6 +10,15 @@ MEMORY
 0x61080000 <--
 */
 
+/*
+Fixed SRAM regions used by the firmware outside of the linker's knowledge (no .data/.bss init):
+  0x61000000 - 0x6100AFFF : page tables, trap scratch page, exception stack, BSS page (see satp.rs)
+  0x61100000 - 0x611003FF : test harness state, e.g. suite selection table (see harness.rs)
+  0x61101000 - 0x611017FF : machine-readable results mailbox (see results.rs)
+  0x61102000 - 0x61103FFF : print! ring buffer state and data (see logbuf.rs)
+  0x61104000 - 0x61106FFF : console trace buffer state and data (see tracebuf.rs)
+*/
+
 REGION_ALIAS("REGION_TEXT", FLASH);
 REGION_ALIAS("REGION_RODATA", FLASH);
 REGION_ALIAS("REGION_DATA", RAM);
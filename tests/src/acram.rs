@@ -0,0 +1,72 @@
+// (c) Copyright CrossBar, Inc. 2024.
+//
+// This documentation describes Open Hardware and is licensed under the
+// [CERN-OHL-W-2.0].
+//
+// You may redistribute and modify this documentation under the terms of the
+// [CERN-OHL- W-2.0 (http://ohwr.org/cernohl)]. This documentation is
+// distributed WITHOUT ANY EXPRESS OR IMPLIED WARRANTY, INCLUDING OF
+// MERCHANTABILITY, SATISFACTORY QUALITY AND FITNESS FOR A PARTICULAR PURPOSE.
+// Please see the [CERN-OHL- W-2.0] for applicable conditions.
+
+//! Encoding of the RRAM access-control (ACRAM) words that `rram.rs` programs.
+
+/// Set when the slot can't be read
+pub const NO_READ: u32 = 1 << 0;
+/// Set when the slot can't be written
+pub const NO_WRITE: u32 = 1 << 1;
+/// Position of the one-hot coreuser field
+pub const USER_SHIFT: u32 = 20;
+/// Width mask of the one-hot coreuser field
+pub const USER_MASK: u32 = 0xF;
+/// Set when the slot can only be written in programming mode
+pub const PROG_ONLY: u32 = 1 << 24;
+
+/// Builds an ACRAM word. `onehot_user_id` is the coreuser as read back in `STATUS_COREUSER`.
+pub fn encode(readable: bool, writeable: bool, onehot_user_id: u32, prog_only: bool) -> u32 {
+    let mut ret = 0;
+    ret |= if !readable { NO_READ } else { 0 };
+    ret |= if !writeable { NO_WRITE } else { 0 };
+    ret |= onehot_user_id << USER_SHIFT;
+    ret |= if prog_only { PROG_ONLY } else { 0 };
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_slot_is_just_the_user() {
+        assert_eq!(encode(true, true, 1, false), 0x0010_0000);
+        assert_eq!(encode(true, true, 8, false), 0x0080_0000);
+    }
+
+    #[test]
+    fn permission_bits_are_inverted() {
+        assert_eq!(encode(false, true, 0, false), NO_READ);
+        assert_eq!(encode(true, false, 0, false), NO_WRITE);
+        assert_eq!(encode(false, false, 0, false), 0b11);
+    }
+
+    #[test]
+    fn prog_only() {
+        assert_eq!(encode(true, true, 0, true), 0x0100_0000);
+        assert_eq!(encode(false, false, 4, true), 0x0140_0003);
+    }
+
+    #[test]
+    fn fields_do_not_overlap() {
+        for user in [1, 2, 4, 8] {
+            for bits in 0..8 {
+                let (readable, writeable, prog_only) = (bits & 1 == 0, bits & 2 == 0, bits & 4 != 0);
+                let word = encode(readable, writeable, user, prog_only);
+                assert_eq!(word & NO_READ == 0, readable);
+                assert_eq!(word & NO_WRITE == 0, writeable);
+                assert_eq!((word >> USER_SHIFT) & USER_MASK, user);
+                assert_eq!(word & PROG_ONLY != 0, prog_only);
+                assert_eq!(word & !(NO_READ | NO_WRITE | USER_MASK << USER_SHIFT | PROG_ONLY), 0);
+            }
+        }
+    }
+}
@@ -29,22 +29,26 @@ use core::{arch::global_asm, convert::TryInto, fmt};
 const TESTS: [AesEcbTest; 4] = [
     // NIST ECB-AES256
     AesEcbTest::new(
+        "nist-256-a",
         &hex!("0000000000000000000000000000000000000000000000000000000000000000"),
         &hex!("014730f80ac625fe84f026c60bfd547d"),
         &hex!("5c9d844ed46f9885085e5d6a4f94c7d7"),
     ),
     AesEcbTest::new(
+        "nist-256-b",
         &hex!("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4"),
         &hex!("ae2d8a571e03ac9c9eb76fac45af8e51"),
         &hex!("591ccb10d410ed26dc5ba74a31362870"),
     ),
     AesEcbTest::new(
+        "nist-256-c",
         &hex!("c47b0294dbbbee0fec4757f22ffeee3587ca4730c3d33b691df38bab076bc558"),
         &hex!("00000000000000000000000000000000"),
         &hex!("46f2fb342d6f0ab477476fc501242c5f"),
     ),
     // From the VexRiscv AES test
     AesEcbTest::new(
+        "vexriscv",
         &hex!("706919a040610517f7fff5272b640467c5067a4bba5778ad6cddcbf473031564"),
         &hex!("0b25f67a11ec9df57305fbe9488ad61b"),
         &hex!("c4b89f454ed855a8a8630bc814877e94"),
@@ -52,7 +56,7 @@ const TESTS: [AesEcbTest; 4] = [
 ];
 
 const AES_TESTS: usize = TESTS.len();
-crate::impl_test!(AesTests, "AES", AES_TESTS);
+crate::impl_test!(AesTests, "AES", AES_TESTS, Aes);
 
 use hex_literal::hex;
 
@@ -67,14 +71,15 @@ fn u8_to_hex_ascii(byte: u8) -> [u8; 2] {
 
 #[repr(align(32))]
 struct AesEcbTest<'a> {
+    name: &'a str,
     key: &'a [u8],
     plaintext: &'a [u8],
     ciphertext: &'a [u8],
 }
 
 impl<'a> AesEcbTest<'a> {
-    pub const fn new(key: &'a [u8], plaintext: &'a [u8], ciphertext: &'a [u8]) -> Self {
-        Self { key, plaintext, ciphertext }
+    pub const fn new(name: &'a str, key: &'a [u8], plaintext: &'a [u8], ciphertext: &'a [u8]) -> Self {
+        Self { name, key, plaintext, ciphertext }
     }
 
     pub fn test(&self) -> Result<(), &'static str> {
@@ -85,14 +90,11 @@ impl<'a> AesEcbTest<'a> {
         print!("Enc: {:x?}\r", &output.as_slice()[..4]);
         let hex_enc = u8_to_hex_ascii(output.as_slice()[0]);
         let hex_check = u8_to_hex_ascii(self.ciphertext[0]);
-        if self.ciphertext.len() != output.len() {
-            Err("encrypt error: ciphertext and output lengths do not match")?;
-        }
-        if self.ciphertext != output.as_slice() {
-            print!("Key:       {:x?}\r", self.key);
-            print!("Plaintext: {:x?}\r", self.plaintext);
-            print!("Reference: {:x?}\r", self.ciphertext);
-            print!("Result:    {:x?}\r", output);
+        if !crate::check::diff_slices("encrypt", output.as_slice(), self.ciphertext) {
+            print!("Key:\r");
+            crate::debug::hexdump(self.key.as_ptr() as usize, self.key.len());
+            print!("Plaintext:\r");
+            crate::debug::hexdump(self.plaintext.as_ptr() as usize, self.plaintext.len());
             Err("encrypt error: ciphertext and output values do not match")?;
         }
         if !((hex_enc[0] == hex_check[0]) && (hex_enc[1] == hex_check[1])) {
@@ -104,12 +106,7 @@ impl<'a> AesEcbTest<'a> {
         // print!("Running decryption\r");
         aes.decrypt_block(&mut output);
         print!("Dec: {:x?}\r", &output.as_slice()[..4]);
-        if self.plaintext.len() != output.len() {
-            Err("decrypt error: plaintext and output lengths do not match")?;
-        }
-        if self.plaintext != output.as_slice() {
-            print!("Plaintext: {:x?}\r", self.plaintext);
-            print!("Result:    {:x?}\r", output);
+        if !crate::check::diff_slices("decrypt", output.as_slice(), self.plaintext) {
             Err("decrypt error: plaintext and output values do not match")?;
         }
 
@@ -131,8 +128,9 @@ impl TestRunner for AesTests {
             if let Err(e) = test.test() {
                 failures += 1;
                 print!("Failed on test: {}\r", e);
+                self.cases.fail(test.name, e);
             } else {
-                self.passing_tests += 1;
+                self.cases.pass(test.name);
             }
         }
         print!("AES {} extensions: {} tests were run with {} errors\r", aes_style, TESTS.len(), failures);
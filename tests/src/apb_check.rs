@@ -11,39 +11,28 @@
 
 // use crate::daric_generated::*;
 
-pub fn singlecheck(title: &str, addr: *mut u32, data: u32) {
-    let mut uart = crate::debug::Uart {};
+use crate::tiny::hex_w;
 
-    uart.tiny_write_str(title);
-    uart.tiny_write_str("::  [");
-    uart.print_hex_word(addr as u32);
-    uart.tiny_write_str("] wr:");
-    uart.print_hex_word(data as u32);
-    uart.tiny_write_str(" | rd:");
+pub fn singlecheck(title: &str, addr: *mut u32, data: u32) {
+    crate::tiny_print!(title, "::  [", hex_w(addr as u32, 8), "] wr:", hex_w(data, 8), " | rd:");
 
     unsafe { addr.write_volatile(data) };
     let r = unsafe { addr.read_volatile() };
 
-    uart.print_hex_word(r);
-    uart.tiny_write_str(" ");
+    crate::tiny_print!(hex_w(r, 8), " ");
     if r != data {
-        uart.tiny_write_str("----[x!]");
+        crate::tiny_print!("----[x!]");
         if r == 0 {
-            uart.tiny_write_str("[0!]");
+            crate::tiny_print!("[0!]");
         }
     }
-    uart.tiny_write_str("\n")
+    crate::tiny_print!("\n")
 }
 
 pub fn singlecheckread(title: &str, addr: *const u32) {
-    let mut uart = crate::debug::Uart {};
-    uart.tiny_write_str(title);
-    uart.tiny_write_str("::  [");
-    uart.print_hex_word(addr as u32);
-    uart.tiny_write_str("] wr:-------- | rd:");
+    crate::tiny_print!(title, "::  [", hex_w(addr as u32, 8), "] wr:-------- | rd:");
     let r = unsafe { addr.read_volatile() };
-    uart.print_hex_word(r);
-    uart.tiny_write_str(" \n");
+    crate::tiny_print!(hex_w(r, 8), " \n");
 }
 
 pub fn apb_test() {
@@ -51,14 +51,12 @@ const BIO_TESTS_FINAL: usize = BIO_TESTS;
 #[cfg(feature = "bio-mul")]
 const BIO_TESTS_FINAL: usize = BIO_TESTS + 1;
 
-crate::impl_test!(BioTests, "BIO", BIO_TESTS_FINAL);
+crate::impl_test!(BioTests, "BIO", BIO_TESTS_FINAL, Bio);
 impl TestRunner for BioTests {
     fn run(&mut self) {
         let id = get_id();
         crate::println!("BIO ID: {:x}", id);
-        if (id >> 16) as usize == BIO_PRIVATE_MEM_LEN {
-            self.passing_tests += 1;
-        } else {
+        if !self.cases.check("get_id", (id >> 16) as usize == BIO_PRIVATE_MEM_LEN, "ID mem size mismatch") {
             crate::println!("Error: ID mem size does not match: {} != {}", id >> 16, BIO_PRIVATE_MEM_LEN);
         }
 
@@ -70,7 +68,7 @@ impl TestRunner for BioTests {
 
         // note: running hello_world up here breaks DMA. need to figure out why
 
-        self.passing_tests += bio_tests::units::hello_multiverse();
+        self.cases.tally("hello_multiverse", bio_tests::units::hello_multiverse(), 1);
 
         bio_tests::dma::dma_filter_off();
         // crate::println!("*** CLKMODE 0 ***");
@@ -88,39 +86,39 @@ impl TestRunner for BioTests {
         // self.passing_tests += bio_tests::dma::dma_multicore(1); // 1
 
         crate::println!("*** CLKMODE 3 ***");
-        self.passing_tests += bio_tests::dma::dma_basic(false, 3); // 4
-        self.passing_tests += bio_tests::dma::dma_basic(true, 3); // 4
-        self.passing_tests += bio_tests::dma::dma_bytes(); // 4
-        self.passing_tests += bio_tests::dma::dma_u16(); // 4
-        self.passing_tests += bio_tests::dma::dma_coincident(3); // 4
-        self.passing_tests += bio_tests::dma::dma_multicore(3); // 1
+        self.cases.tally("dma_basic", bio_tests::dma::dma_basic(false, 3), 4);
+        self.cases.tally("dma_basic_concurrent", bio_tests::dma::dma_basic(true, 3), 4);
+        self.cases.tally("dma_bytes", bio_tests::dma::dma_bytes(), 4);
+        self.cases.tally("dma_u16", bio_tests::dma::dma_u16(), 4);
+        self.cases.tally("dma_coincident", bio_tests::dma::dma_coincident(3), 4);
+        self.cases.tally("dma_multicore", bio_tests::dma::dma_multicore(3), 1);
         // return;
 
-        self.passing_tests += bio_tests::units::hello_world();
-        self.passing_tests += bio_tests::arith::stack_test();
+        self.cases.tally("hello_world", bio_tests::units::hello_world(), 1);
+        self.cases.tally("stack_test", bio_tests::arith::stack_test(), 1);
         #[cfg(feature = "bio-mul")]
         {
             // safety: this is safe only if the target supports multiplication
-            self.passing_tests += unsafe { bio_tests::arith::mac_test() }; // 1
+            self.cases.tally("mac_test", unsafe { bio_tests::arith::mac_test() }, 1);
         }
 
-        self.passing_tests += bio_tests::units::aclk_tests();
+        self.cases.tally("aclk_tests", bio_tests::units::aclk_tests(), 1);
 
-        self.passing_tests += bio_tests::dma::filter_test();
+        self.cases.tally("filter_test", bio_tests::dma::filter_test(), 1);
 
         bio_tests::dma::dma_filter_off();
-        self.passing_tests += bio_tests::dma::dmareq_test();
-        self.passing_tests += bio_tests::units::event_aliases();
-        self.passing_tests += bio_tests::units::fifo_alias_tests();
+        self.cases.tally("dmareq_test", bio_tests::dma::dmareq_test(), 1);
+        self.cases.tally("event_aliases", bio_tests::units::event_aliases(), 1);
+        self.cases.tally("fifo_alias_tests", bio_tests::units::fifo_alias_tests(), 1);
 
-        self.passing_tests += bio_tests::units::fifo_basic();
-        self.passing_tests += bio_tests::units::host_fifo_tests();
+        self.cases.tally("fifo_basic", bio_tests::units::fifo_basic(), 1);
+        self.cases.tally("host_fifo_tests", bio_tests::units::host_fifo_tests(), 1);
 
-        self.passing_tests += bio_tests::spi::spi_test();
-        self.passing_tests += bio_tests::i2c::i2c_test();
-        self.passing_tests += bio_tests::i2c::complex_i2c_test();
+        self.cases.tally("spi_test", bio_tests::spi::spi_test(), 1);
+        self.cases.tally("i2c_test", bio_tests::i2c::i2c_test(), 1);
+        self.cases.tally("complex_i2c_test", bio_tests::i2c::complex_i2c_test(), 1);
 
         // note: this test runs without any cores, as all FIFO levels can be tested from the host directly
-        self.passing_tests += bio_tests::units::fifo_level_tests();
+        self.cases.tally("fifo_level_tests", bio_tests::units::fifo_level_tests(), 1);
     }
 }
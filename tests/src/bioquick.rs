@@ -2,7 +2,8 @@ use utralib::generated::*;
 use xous_bio_bdma::bio_tests::dma::TestPattern;
 use xous_bio_bdma::*;
 
-use crate::print;
+use crate::exit::{self, ExitStatus};
+use crate::{tiny, tiny_println};
 
 #[allow(dead_code)]
 pub unsafe fn bio_bypass() -> ! {
@@ -130,7 +131,7 @@ pub unsafe fn bio_bypass() -> ! {
 
     // clocks setup. now try DMA
     bio_tests::dma::dma_filter_off();
-    print!("DMA quick\r");
+    tiny_println!("DMA quick");
     const TEST_LEN: usize = 64;
 
     // clear prior test config state
@@ -153,7 +154,7 @@ pub unsafe fn bio_bypass() -> ! {
     bio_ss.bio.wo(utra::bio_bdma::SFR_QDIV3, 0x1_0000);
     // start the machine
     bio_ss.bio.wo(utra::bio_bdma::SFR_CTRL, 0xFFF); // start all the machines to get them to stop being stupid
-    print!("st\r");
+    tiny_println!("st");
     bio_ss.bio.wo(utra::bio_bdma::SFR_CTRL, 0x111); // now restart the machine of interest
 
     let mut main_mem_src: [u32; TEST_LEN] = [0u32; TEST_LEN];
@@ -166,27 +167,25 @@ pub unsafe fn bio_bypass() -> ! {
         unsafe { core::slice::from_raw_parts_mut((utralib::HW_IFRAM1_MEM + 10000) as *mut u32, TEST_LEN) };
     ifram_src.fill(0);
     ifram_dst.fill(0);
-    basic_u32(&mut bio_ss, &mut main_mem_src, &mut main_mem_dst, 0, "Main->main", false);
+    // basic_u32() returns 1 on a pass
+    let mut failures = 0;
+    failures += 1 - basic_u32(&mut bio_ss, &mut main_mem_src, &mut main_mem_dst, 0, "Main->main", false);
 
     main_mem_src.fill(0);
     main_mem_dst.fill(0);
-    basic_u32(&mut bio_ss, ifram_src, &mut main_mem_dst, 0x40, "ifram0->main", false);
+    failures += 1 - basic_u32(&mut bio_ss, ifram_src, &mut main_mem_dst, 0x40, "ifram0->main", false);
 
     ifram_src.fill(0);
     main_mem_dst.fill(0);
-    basic_u32(&mut bio_ss, &mut main_mem_src, ifram_dst, 0x80, "Main->ifram1", false);
+    failures += 1 - basic_u32(&mut bio_ss, &mut main_mem_src, ifram_dst, 0x80, "Main->ifram1", false);
 
     main_mem_src.fill(0);
     ifram_dst.fill(0);
-    basic_u32(&mut bio_ss, ifram_src, ifram_dst, 0xC0, "ifram0->ifram1", false);
+    failures += 1 - basic_u32(&mut bio_ss, ifram_src, ifram_dst, 0xC0, "ifram0->ifram1", false);
 
-    print!("DMA test done.\r");
+    tiny_println!("DMA test done.");
 
-    // this sequence triggers an end of simulation on s32
-    let mut test_cfg = CSR::new(utra::csrtest::HW_CSRTEST_BASE as *mut u32);
-    test_cfg.wo(utra::csrtest::WTEST, 0xc0ded02e);
-    test_cfg.wo(utra::csrtest::WTEST, 0xc0de600d);
-    loop {}
+    exit::end_run(if failures == 0 { ExitStatus::Pass } else { ExitStatus::Fail }, failures as u32)
 }
 
 fn basic_u32(
@@ -198,7 +197,7 @@ fn basic_u32(
     concurrent: bool,
 ) -> usize {
     assert!(src.len() == dst.len());
-    print!("  - {}\r", name);
+    tiny_println!("  - ", name);
     let mut tp = TestPattern::new(Some(seed));
     for d in src.iter_mut() {
         *d = tp.next();
@@ -216,7 +215,7 @@ fn basic_u32(
         for (i, &d) in src.iter().enumerate() {
             let rbk = unsafe { dst.as_ptr().add(i).read_volatile() };
             if rbk != d {
-                print!("{} DMA err @{}, {:x} rbk: {:x}\r", name, i, d, rbk);
+                tiny_println!(name, " DMA err @", i, ", ", tiny::hex(d), " rbk: ", tiny::hex(rbk));
                 pass = 0;
             }
         }
@@ -238,7 +237,7 @@ fn basic_u32(
         for (i, &d) in src.iter().enumerate() {
             let rbk = unsafe { dst.as_ptr().add(i).read_volatile() };
             if rbk != d {
-                print!("(c) {} DMA err @{}, {:x} rbk: {:x}\r", name, i, d, rbk);
+                tiny_println!("(c) ", name, " DMA err @", i, ", ", tiny::hex(d), " rbk: ", tiny::hex(rbk));
                 pass = 0;
             }
         }
@@ -31,7 +31,7 @@ const ROWS: usize = 8;
 const COLS: usize = 64;
 
 const CAM_TESTS: usize = 1;
-crate::impl_test!(CamTests, "Camera", CAM_TESTS);
+crate::impl_test!(CamTests, "Camera", CAM_TESTS, Cam);
 
 pub struct TestCamera {
     csr: CSR<u32>,
@@ -195,7 +195,9 @@ impl TestRunner for CamTests {
         iox.set_ports_from_pio_bitmask(0xFFFF_FFFF);
         println!("piosel {:x}", iox.csr.r(utra::iox::SFR_PIOSEL));
 
-        // always "passes"
-        self.passing_tests += 1;
+        // this suite is only checked by inspecting waveforms, so nothing is verified here
+        self.cases.skip("frames", "checked by inspecting waveforms");
     }
+
+    fn buffered_log(&self) -> bool { true }
 }
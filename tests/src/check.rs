@@ -0,0 +1,138 @@
+// (c) Copyright CrossBar, Inc. 2024.
+//
+// This documentation describes Open Hardware and is licensed under the
+// [CERN-OHL-W-2.0].
+//
+// You may redistribute and modify this documentation under the terms of the
+// [CERN-OHL- W-2.0 (http://ohwr.org/cernohl)]. This documentation is
+// distributed WITHOUT ANY EXPRESS OR IMPLIED WARRANTY, INCLUDING OF
+// MERCHANTABILITY, SATISFACTORY QUALITY AND FITNESS FOR A PARTICULAR PURPOSE.
+// Please see the [CERN-OHL- W-2.0] for applicable conditions.
+
+//! Comparison helpers that report mismatches the same way in every suite.
+//!
+//! A mismatch is printed as a hex diff of the first `MAX_DIFF_WORDS` differing words, with their
+//! index and address, and is also reported as events so that it shows up in simulation traces: a
+//! `check::MISMATCH` failure for each word shown, then the total number of mismatching words.
+//!
+//! `check_eq!` and `check_slice_eq!` record the comparison as a case; `diff_values()`,
+//! `diff_slices()` and `Diff` only report, for routines that tally their own results.
+
+use crate::event::{self, Event, Subsystem};
+use crate::*;
+
+/// Number of mismatching words shown for one comparison
+pub const MAX_DIFF_WORDS: usize = 8;
+
+/// A value that can be compared and shown as a hex word.
+pub trait Word: Copy + PartialEq {
+    fn bits(self) -> u64;
+}
+
+macro_rules! impl_word {
+    ($($t:ty),*) => {
+        $(impl Word for $t {
+            fn bits(self) -> u64 { self as u64 }
+        })*
+    };
+}
+impl_word!(u8, u16, u32, u64, usize);
+
+/// Accumulates mismatches between actual and expected words, showing the first few.
+pub struct Diff {
+    what: &'static str,
+    checked: usize,
+    mismatches: usize,
+}
+
+impl Diff {
+    pub fn new(what: &'static str) -> Self { Diff { what, checked: 0, mismatches: 0 } }
+
+    /// Compares one word. `index` is its position in the data under test, and `addr` is where
+    /// `actual` was read from, if it came from memory.
+    pub fn word<T: Word>(&mut self, index: usize, addr: Option<usize>, actual: T, expected: T) -> bool {
+        self.checked += 1;
+        if actual == expected {
+            return true;
+        }
+        if self.mismatches == 0 {
+            println!("  {} mismatch:", self.what);
+        }
+        if self.mismatches < MAX_DIFF_WORDS {
+            let width = core::mem::size_of::<T>() * 2;
+            match addr {
+                Some(addr) => print!("    [{}] {:08x}:", index, addr),
+                None => print!("    [{}]:", index),
+            }
+            println!(
+                " got {:0w$x} expected {:0w$x} (xor {:0w$x})",
+                actual.bits(),
+                expected.bits(),
+                actual.bits() ^ expected.bits(),
+                w = width
+            );
+            Subsystem::Check.report(
+                Event::Fail(event::check::MISMATCH),
+                &[addr.unwrap_or(0) as u32, actual.bits() as u32, expected.bits() as u32],
+            );
+        }
+        self.mismatches += 1;
+        false
+    }
+
+    pub fn mismatches(&self) -> usize { self.mismatches }
+
+    /// Ends the comparison, summarizing any mismatches. Returns whether everything matched.
+    pub fn finish(self) -> bool {
+        if self.mismatches != 0 {
+            if self.mismatches > MAX_DIFF_WORDS {
+                println!("    ... {} more", self.mismatches - MAX_DIFF_WORDS);
+            }
+            println!("  {}: {} of {} words differ", self.what, self.mismatches, self.checked);
+            Subsystem::Check.report(Event::Value(event::check::MISMATCHES), &[self.mismatches as u32]);
+        }
+        self.mismatches == 0
+    }
+}
+
+/// Compares two values, reporting them if they differ. Returns whether they matched. The values
+/// have no address, so the mismatch is reported with an address of 0.
+pub fn diff_values<T: Word>(what: &'static str, actual: T, expected: T) -> bool {
+    let mut diff = Diff::new(what);
+    diff.word(0, None, actual, expected);
+    diff.finish()
+}
+
+/// Compares two slices element by element, reporting the first mismatches along with the address
+/// of each element of `actual`. Slices of different lengths never match.
+pub fn diff_slices<T: Word>(what: &'static str, actual: &[T], expected: &[T]) -> bool {
+    let mut diff = Diff::new(what);
+    for (i, (a, e)) in actual.iter().zip(expected.iter()).enumerate() {
+        diff.word(i, Some(a as *const T as usize), *a, *e);
+    }
+    let lengths_match = actual.len() == expected.len();
+    if !lengths_match {
+        println!("  {}: length {} != expected {}", what, actual.len(), expected.len());
+    }
+    diff.finish() && lengths_match
+}
+
+/// Records case `$name` in the `CaseLog` `$cases`: a pass if `$actual == $expected`, otherwise a
+/// failure with both values reported. Evaluates to whether they matched.
+#[macro_export]
+macro_rules! check_eq {
+    ($cases:expr, $name:expr, $actual:expr, $expected:expr) => {{
+        let ok = $crate::check::diff_values($name, $actual, $expected);
+        $cases.check($name, ok, concat!(stringify!($actual), " != ", stringify!($expected)))
+    }};
+}
+
+/// Records case `$name` in the `CaseLog` `$cases`: a pass if the two slices are equal, otherwise a
+/// failure with a hex diff of the first mismatching words. Evaluates to whether they matched.
+#[macro_export]
+macro_rules! check_slice_eq {
+    ($cases:expr, $name:expr, $actual:expr, $expected:expr) => {{
+        let ok = $crate::check::diff_slices($name, &$actual[..], &$expected[..]);
+        $cases.check($name, ok, concat!(stringify!($actual), " differs from ", stringify!($expected)))
+    }};
+}
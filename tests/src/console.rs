@@ -0,0 +1,230 @@
+// (c) Copyright CrossBar, Inc. 2024.
+//
+// This documentation describes Open Hardware and is licensed under the
+// [CERN-OHL-W-2.0].
+//
+// You may redistribute and modify this documentation under the terms of the
+// [CERN-OHL- W-2.0 (http://ohwr.org/cernohl)]. This documentation is
+// distributed WITHOUT ANY EXPRESS OR IMPLIED WARRANTY, INCLUDING OF
+// MERCHANTABILITY, SATISFACTORY QUALITY AND FITNESS FOR A PARTICULAR PURPOSE.
+// Please see the [CERN-OHL- W-2.0] for applicable conditions.
+
+//! Interactive serial console, for driving the suites on silicon without reflashing.
+//!
+//! Entered after the normal run when `FLAG_CONSOLE` is set in the selection table; leaving it
+//! continues with the summary and result record. Input is read from the UDMA UART (PD13/PD14),
+//! and output goes to the DUART like everything else.
+
+use crate::harness::{self, Exit};
+use crate::*;
+
+const LINE_LEN: usize = 80;
+/// Default length of a `dump`, in bytes
+const DEFAULT_DUMP_LEN: usize = 64;
+
+const HELP: &str = "commands:\r
+  list                  list the registered suites\r
+  run <name|id>         run one suite\r
+  peek <addr> [count]   read words\r
+  poke <addr> <value>   write a word, then read it back\r
+  dump <addr> [len]     dump memory as hex and ASCII\r
+  log [<module|all> <level|default>]  show or set log thresholds\r
+  exit                  leave the console and print the summary\r";
+
+/// Runs the console if `FLAG_CONSOLE` is set. `passed` is the mask of suites that have passed so
+/// far; returns it updated with any suites run from the console.
+pub fn console(tests: &mut [&mut dyn Test], mut passed: u32) -> u32 {
+    if (harness::select_flags() & harness::FLAG_CONSOLE) == 0 {
+        return passed;
+    }
+    init::setup_console_rx();
+    println!("Test console; type 'help' for a list of commands");
+    let mut buf = [0u8; LINE_LEN];
+    loop {
+        print!("> ");
+        let line = read_line(&mut buf);
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command,
+            None => continue,
+        };
+        match command {
+            "help" | "?" => println!("{}", HELP),
+            "list" => list(tests),
+            "run" => {
+                // suite names can contain spaces, so the name is the rest of the line
+                let name = line.trim_start()["run".len()..].trim();
+                passed = run(tests, name, passed);
+            }
+            "peek" => match (words.next().and_then(parse_num), words.next().map(parse_num)) {
+                (Some(addr), None) => peek(addr, 1),
+                (Some(addr), Some(Some(count))) => peek(addr, count),
+                _ => println!("usage: peek <addr> [count]"),
+            },
+            "poke" => match (words.next().and_then(parse_num), words.next().and_then(parse_num)) {
+                (Some(addr), Some(value)) => poke(addr, value),
+                _ => println!("usage: poke <addr> <value>"),
+            },
+            "dump" => match (words.next().and_then(parse_num), words.next().map(parse_num)) {
+                (Some(addr), None) => debug::hexdump(addr as usize, DEFAULT_DUMP_LEN),
+                (Some(addr), Some(Some(len))) => debug::hexdump(addr as usize, len as usize),
+                _ => println!("usage: dump <addr> [len]"),
+            },
+            "log" => match (words.next(), words.next()) {
+                (None, _) => show_log_levels(),
+                (Some(module), Some(level)) => set_log_level(module, level),
+                _ => println!("usage: log [<module|all> <level|default>]"),
+            },
+            "exit" | "quit" => break,
+            _ => println!("unknown command '{}'; type 'help' for a list", command),
+        }
+    }
+    passed
+}
+
+/// Reads one line, echoing it and handling backspace. Non-printable input is ignored.
+fn read_line(buf: &mut [u8; LINE_LEN]) -> &str {
+    let uart = debug::Uart {};
+    let mut len = 0;
+    loop {
+        let c = match uart.getc() {
+            Some(c) => c,
+            None => continue,
+        };
+        match c {
+            b'\r' | b'\n' => {
+                println!();
+                break;
+            }
+            0x08 | 0x7f => {
+                if len > 0 {
+                    len -= 1;
+                    uart.putc(0x08);
+                    uart.putc(b' ');
+                    uart.putc(0x08);
+                }
+            }
+            0x20..=0x7e if len < buf.len() => {
+                buf[len] = c;
+                len += 1;
+                uart.putc(c);
+            }
+            _ => (),
+        }
+    }
+    core::str::from_utf8(&buf[..len]).unwrap_or("")
+}
+
+/// Parses a number in decimal, or in hex with a `0x` prefix. Underscores are ignored.
+fn parse_num(s: &str) -> Option<u32> {
+    let (digits, radix) = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => (hex, 16),
+        None => (s, 10),
+    };
+    let mut value: u32 = 0;
+    let mut any = false;
+    for c in digits.chars().filter(|&c| c != '_') {
+        value = value.checked_mul(radix)?.checked_add(c.to_digit(radix)?)?;
+        any = true;
+    }
+    if any { Some(value) } else { None }
+}
+
+fn list(tests: &[&mut dyn Test]) {
+    for test in tests.iter() {
+        println!(
+            "{:>2} {:<24} {:<8} {}",
+            test.id() as u32,
+            test.name(),
+            if test.is_enabled() { "enabled" } else { "-" },
+            test.outcome().tag()
+        );
+    }
+}
+
+/// Runs the suite named `name`, or with `SuiteId` `name`. Its earlier results are discarded.
+fn run(tests: &mut [&mut dyn Test], name: &str, mut passed: u32) -> u32 {
+    let id = parse_num(name);
+    let index = tests.iter().position(|t| Some(t.id() as u32) == id || t.name().eq_ignore_ascii_case(name));
+    let i = match index {
+        Some(i) => i,
+        None => {
+            println!("no suite named '{}'", name);
+            return passed;
+        }
+    };
+    if let Some(prereq) = harness::blocking_prerequisite(tests, tests[i].requires(), passed) {
+        println!("{} requires {}; run that first", tests[i].name(), prereq);
+        return passed;
+    }
+    let test = &mut tests[i];
+    test.set_enable(true);
+    test.cases_mut().clear();
+    println!(">>> Running {}", test.name());
+    if harness::run_timed(&mut **test, passed) == Exit::Returned {
+        println!("<<< {} done", test.name());
+    }
+    let outcome = test.outcome();
+    if outcome.is_pass() {
+        passed |= test.id().mask();
+    } else {
+        passed &= !test.id().mask();
+    }
+    println!(
+        "{}: {} ({}/{} passing, {} ticks)",
+        test.name(),
+        outcome.tag(),
+        test.passing_tests(),
+        test.total_tests(),
+        test.stats().elapsed_ticks()
+    );
+    for case in test.cases().failures() {
+        println!("  FAIL {}: {}", case.name, case.msg);
+    }
+    passed
+}
+
+fn show_log_levels() {
+    for (m, name) in log::MODULES.iter().enumerate() {
+        let level = log::threshold(m);
+        if level == log::BUILD_LEVELS[m] {
+            println!("{:<10} {}", name, level.name());
+        } else {
+            println!("{:<10} {} (built with {})", name, level.name(), log::BUILD_LEVELS[m].name());
+        }
+    }
+}
+
+fn set_log_level(module: &str, level: &str) {
+    let level = match level {
+        "default" => None,
+        _ => match log::Level::from_name(level) {
+            Some(level) => Some(level),
+            None => {
+                println!("levels: off, error, warn, info, debug, trace, default");
+                return;
+            }
+        },
+    };
+    match module {
+        "all" => log::set_level(None, level),
+        _ => match log::MODULES.iter().position(|&m| m == module) {
+            Some(m) => log::set_level(Some(m), level),
+            None => println!("unknown module '{}'; 'log' lists them", module),
+        },
+    }
+}
+
+fn peek(addr: u32, count: u32) {
+    let base = (addr & !3) as *const u32;
+    for i in 0..count as usize {
+        let value = unsafe { base.add(i).read_volatile() };
+        println!("{:08x}: {:08x}", base as usize + i * 4, value);
+    }
+}
+
+fn poke(addr: u32, value: u32) {
+    let p = (addr & !3) as *mut u32;
+    unsafe { p.write_volatile(value) };
+    println!("{:08x}: {:08x} (read back {:08x})", p as usize, value, unsafe { p.read_volatile() });
+}
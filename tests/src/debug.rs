@@ -9,21 +9,18 @@
 // MERCHANTABILITY, SATISFACTORY QUALITY AND FITNESS FOR A PARTICULAR PURPOSE.
 // Please see the [CERN-OHL- W-2.0] for applicable conditions.
 
+//! Console driver for the DUART, which backs `print!`.
+//!
+//! The DUART can only transmit; `getc()` reads from the UDMA UART instead. Output sent through
+//! the helpers and `Write` is mirrored to the SRAM trace buffer (see `tracebuf.rs`); `putc()` and
+//! `try_putc()` only drive the DUART.
+
 use utralib::generated::*;
-pub struct Uart {
-    // pub base: *mut u32,
-}
 
-pub mod duart {
-    pub const UART_DOUT: utralib::Register = utralib::Register::new(0, 0xff);
-    pub const UART_DOUT_DOUT: utralib::Field = utralib::Field::new(8, 0, UART_DOUT);
-    pub const UART_CTL: utralib::Register = utralib::Register::new(1, 1);
-    pub const UART_CTL_EN: utralib::Field = utralib::Field::new(1, 0, UART_CTL);
-    pub const UART_BUSY: utralib::Register = utralib::Register::new(2, 1);
-    pub const UART_BUSY_BUSY: utralib::Field = utralib::Field::new(1, 0, UART_BUSY);
+/// `SFR_ETUC` value set by `init::early_init()`, in DUART clocks per bit
+pub const DEFAULT_ETUC: u32 = 24;
 
-    pub const HW_DUART_BASE: usize = 0x4004_2000;
-}
+pub struct Uart {}
 
 #[allow(dead_code)]
 impl Uart {
@@ -31,7 +28,7 @@ impl Uart {
         let nyb = d & 0xF;
         let c = if nyb < 10 { nyb + 0x30 } else { nyb + 0x61 - 10 };
         assert!(c >= 0x30, "conversion failed!");
-        self.putc(c);
+        self.out(c);
     }
 
     pub fn put_hex(&mut self, c: u8) {
@@ -40,8 +37,8 @@ impl Uart {
     }
 
     pub fn newline(&mut self) {
-        self.putc(0xa);
-        self.putc(0xd);
+        self.out(0xa);
+        self.out(0xd);
     }
 
     pub fn print_hex_word(&mut self, word: u32) {
@@ -50,40 +47,171 @@ impl Uart {
         }
     }
 
+    fn csr(&self) -> CSR<u32> { CSR::new(utra::duart::HW_DUART_BASE as *mut u32) }
+
+    /// Sets the bit time to `etuc` DUART clocks and enables the DUART.
+    pub fn init(&self, etuc: u32) {
+        let mut uart = self.csr();
+        uart.wo(utra::duart::SFR_CR, 0);
+        uart.wfo(utra::duart::SFR_ETUC_SFR_ETUC, etuc);
+        uart.wo(utra::duart::SFR_CR, 1);
+    }
+
+    /// Sets the baud rate, given the frequency of the DUART's clock.
+    pub fn set_baud(&self, baud: u32, clk_hz: u32) { self.init((clk_hz + baud / 2) / baud); }
+
     pub fn putc(&self, c: u8) {
-        let base = duart::HW_DUART_BASE as *mut u32;
-        let mut uart = CSR::new(base);
+        let mut uart = self.csr();
 
-        if uart.rf(duart::UART_CTL_EN) == 0 {
-            uart.wfo(duart::UART_CTL_EN, 1);
+        if uart.rf(utra::duart::SFR_CR_SFR_CR) == 0 {
+            uart.wfo(utra::duart::SFR_CR_SFR_CR, 1);
         }
-        while uart.rf(duart::UART_BUSY_BUSY) != 0 {
+        while uart.rf(utra::duart::SFR_SR_SFR_SR) != 0 {
             // spin wait
         }
-        uart.wfo(duart::UART_DOUT_DOUT, c as u32);
+        uart.wfo(utra::duart::SFR_TXD_SFR_TXD, c as u32);
     }
 
-    pub fn getc(&self) -> Option<u8> {
-        unimplemented!();
+    /// Sends `c` if the DUART is idle. Returns false, without waiting, if it is busy.
+    pub fn try_putc(&self, c: u8) -> bool {
+        let mut uart = self.csr();
+        if uart.rf(utra::duart::SFR_CR_SFR_CR) == 0 || uart.rf(utra::duart::SFR_SR_SFR_SR) != 0 {
+            return false;
+        }
+        uart.wfo(utra::duart::SFR_TXD_SFR_TXD, c as u32);
+        true
     }
 
-    pub fn tiny_write_str(&mut self, s: &str) {
+    /// Stress mode for the DUART's bus interface: writes `s` with back-to-back busy polls and
+    /// writes, and without the enable check, so that the busy readback catches the DUART
+    /// mid-character instead of always reading "not busy".
+    pub fn fast_write_str(&self, s: &str) {
+        let base = utra::duart::HW_DUART_BASE as *mut u32;
         for c in s.bytes() {
+            unsafe {
+                while base.add(utra::duart::SFR_SR.offset()).read_volatile() != 0 {
+                    // wait
+                }
+                base.add(utra::duart::SFR_TXD.offset()).write_volatile(c as u32);
+            }
+        }
+    }
+
+    /// Returns the next received byte, if any. The DUART can only transmit, so input comes from
+    /// the UDMA UART, which must first be set up with `init::setup_console_rx()`.
+    pub fn getc(&self) -> Option<u8> {
+        let uart = CSR::new(utra::udma_uart_1::HW_UDMA_UART_1_BASE as *mut u32);
+        if uart.rf(utra::udma_uart_1::REG_VALID_R_UART_RX_DATA_VALID) != 0 {
+            Some(uart.rf(utra::udma_uart_1::REG_DATA_R_UART_RX_DATA) as u8)
+        } else {
+            None
+        }
+    }
+
+    pub fn tiny_write_str(&mut self, s: &str) { self.put_bytes(s.as_bytes()); }
+
+    /// Sends raw bytes to every console sink.
+    pub fn put_bytes(&mut self, bytes: &[u8]) {
+        crate::tracebuf::mirror(bytes);
+        for &c in bytes {
             self.putc(c);
         }
     }
+
+    /// Sends `c` to every console sink.
+    fn out(&self, c: u8) {
+        crate::tracebuf::mirror(&[c]);
+        self.putc(c);
+    }
 }
 
 use core::fmt::{Error, Write};
 impl Write for Uart {
     fn write_str(&mut self, s: &str) -> Result<(), Error> {
-        for c in s.bytes() {
-            self.putc(c);
-        }
+        self.tiny_write_str(s);
         Ok(())
     }
 }
 
+/// Words shown per `hexdump()`/`memdiff()` row
+const ROW_WORDS: usize = 4;
+
+/// Reads one row of up to `ROW_WORDS` words starting at `base`.
+fn read_row(base: *const u32, count: usize) -> [u32; ROW_WORDS] {
+    let mut row = [0u32; ROW_WORDS];
+    for (i, word) in row.iter_mut().enumerate().take(count) {
+        *word = unsafe { base.add(i).read_volatile() };
+    }
+    row
+}
+
+/// Prints `words` as an address-annotated row with an ASCII column.
+fn print_row(prefix: &str, addr: usize, words: &[u32]) {
+    crate::print!("{}{:08x}:", prefix, addr);
+    for word in words {
+        crate::print!(" {:08x}", word);
+    }
+    for _ in words.len()..ROW_WORDS {
+        crate::print!("         ");
+    }
+    crate::print!("  |");
+    for word in words {
+        for &b in word.to_le_bytes().iter() {
+            crate::print!("{}", if (0x20..0x7f).contains(&b) { b as char } else { '.' });
+        }
+    }
+    crate::println!("|");
+}
+
+/// Number of words covering `len` bytes from `addr`, once `addr` is rounded down to a word.
+fn span_words(addr: usize, len: usize) -> usize { ((addr & 3) + len + 3) / 4 }
+
+/// Prints `len` bytes from `addr` as rows of words with an ASCII column. Memory is read a whole
+/// word at a time, so registers can be dumped too.
+pub fn hexdump(addr: usize, len: usize) {
+    let base = (addr & !3) as *const u32;
+    let words = span_words(addr, len);
+    for row in (0..words).step_by(ROW_WORDS) {
+        let n = (words - row).min(ROW_WORDS);
+        let row_base = unsafe { base.add(row) };
+        print_row("", row_base as usize, &read_row(row_base, n)[..n]);
+    }
+}
+
+/// Compares `len` bytes at `a` and `b` a word at a time. Each row that differs is printed for
+/// both, with the differing words marked. Returns the number of differing words.
+pub fn memdiff(a: usize, b: usize, len: usize) -> usize {
+    let (base_a, base_b) = ((a & !3) as *const u32, (b & !3) as *const u32);
+    let words = span_words(a, len);
+    let mut differing = 0;
+    for row in (0..words).step_by(ROW_WORDS) {
+        let n = (words - row).min(ROW_WORDS);
+        let (row_a, row_b) = unsafe { (base_a.add(row), base_b.add(row)) };
+        let (wa, wb) = (read_row(row_a, n), read_row(row_b, n));
+        if wa[..n] == wb[..n] {
+            continue;
+        }
+        print_row("- ", row_a as usize, &wa[..n]);
+        print_row("+ ", row_b as usize, &wb[..n]);
+        crate::print!("           ");
+        for (x, y) in wa[..n].iter().zip(wb[..n].iter()) {
+            if x != y {
+                crate::print!(" ^^^^^^^^");
+                differing += 1;
+            } else {
+                crate::print!("         ");
+            }
+        }
+        crate::println!();
+    }
+    if differing == 0 {
+        crate::println!("{:08x} and {:08x}: {} bytes match", a, b, len);
+    } else {
+        crate::println!("{:08x} and {:08x}: {} of {} words differ", a, b, differing, words);
+    }
+    differing
+}
+
 #[macro_use]
 pub mod debug_print_hardware {
     #[macro_export]
@@ -91,7 +219,7 @@ pub mod debug_print_hardware {
     {
         ($($args:tt)+) => ({
                 use core::fmt::Write;
-                let _ = write!(crate::debug::Uart {}, $($args)+);
+                let _ = write!(crate::logbuf::Logger {}, $($args)+);
         });
     }
 }
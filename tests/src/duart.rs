@@ -1,50 +0,0 @@
-// (c) Copyright CrossBar, Inc. 2024.
-//
-// This documentation describes Open Hardware and is licensed under the
-// [CERN-OHL-W-2.0].
-//
-// You may redistribute and modify this documentation under the terms of the
-// [CERN-OHL- W-2.0 (http://ohwr.org/cernohl)]. This documentation is
-// distributed WITHOUT ANY EXPRESS OR IMPLIED WARRANTY, INCLUDING OF
-// MERCHANTABILITY, SATISFACTORY QUALITY AND FITNESS FOR A PARTICULAR PURPOSE.
-// Please see the [CERN-OHL- W-2.0] for applicable conditions.
-
-pub mod duart {
-    pub const UART_DOUT: utralib::Register = utralib::Register::new(0, 0xff);
-    pub const UART_DOUT_DOUT: utralib::Field = utralib::Field::new(8, 0, UART_DOUT);
-    pub const UART_CTL: utralib::Register = utralib::Register::new(1, 1);
-    pub const UART_CTL_EN: utralib::Field = utralib::Field::new(1, 0, UART_CTL);
-    pub const UART_BUSY: utralib::Register = utralib::Register::new(2, 1);
-    pub const UART_BUSY_BUSY: utralib::Field = utralib::Field::new(1, 0, UART_BUSY);
-
-    pub const HW_DUART_BASE: usize = 0x4004_2000;
-}
-pub struct Duart {
-    csr: utralib::CSR::<u32>,
-}
-impl Duart {
-    pub fn new() -> Self {
-        let mut duart_csr = utralib::CSR::new(duart::HW_DUART_BASE as *mut u32);
-        duart_csr.wfo(duart::UART_CTL_EN, 1);
-        Duart {
-            csr: duart_csr,
-        }
-    }
-    pub fn putc(&mut self, ch: char) {
-        while self.csr.rf(duart::UART_BUSY_BUSY) != 0 {
-            // spin wait
-        }
-        // the code here bypasses a lot of checks to simulate very fast write cycles so
-        // that the read waitback actually returns something other than not busy.
-        // unsafe {(duart::HW_DUART_BASE as *mut u32).write_volatile(ch as u32) }; // this line really ensures we have to readback something, but it causes double-printing
-        while unsafe{(duart::HW_DUART_BASE as *mut u32).add(2).read_volatile()} != 0 {
-            // wait
-        }
-        unsafe {(duart::HW_DUART_BASE as *mut u32).write_volatile(ch as u32) };
-    }
-    pub fn puts(&mut self, s: &str) {
-        for c in s.as_bytes() {
-            self.putc(*c as char);
-        }
-    }
-}
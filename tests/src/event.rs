@@ -0,0 +1,208 @@
+// (c) Copyright CrossBar, Inc. 2024.
+//
+// This documentation describes Open Hardware and is licensed under the
+// [CERN-OHL-W-2.0].
+//
+// You may redistribute and modify this documentation under the terms of the
+// [CERN-OHL- W-2.0 (http://ohwr.org/cernohl)]. This documentation is
+// distributed WITHOUT ANY EXPRESS OR IMPLIED WARRANTY, INCLUDING OF
+// MERCHANTABILITY, SATISFACTORY QUALITY AND FITNESS FOR A PARTICULAR PURPOSE.
+// Please see the [CERN-OHL- W-2.0] for applicable conditions.
+
+//! Structured events for the `csrtest::WTEST` trace.
+//!
+//! Each event is a tag word followed by up to 15 payload words, all written through
+//! `report_api`. The tag is laid out as:
+//!
+//! | Bits  | Field |
+//! |-------|-------|
+//! | 31:28 | `0xE`, marks a tag |
+//! | 27:24 | `Event` kind |
+//! | 23:16 | `Subsystem` |
+//! | 15:12 | number of payload words that follow |
+//! | 11:0  | code: a step number, test index, or one of the per-subsystem constants below |
+//!
+//! `cargo xtask wtest` turns a simulator's trace of `WTEST` writes back into a timeline. Its tables
+//! in `xtask/src/wtest.rs` must be kept in sync with this file. Note that an interrupt taken while
+//! a payload is being written can interleave its own events with it.
+
+#![allow(dead_code)] // some codes are only reported with optional features
+
+use crate::*;
+
+pub const TAG: u32 = 0xE000_0000;
+pub const TAG_MASK: u32 = 0xF000_0000;
+pub const MAX_PAYLOAD: usize = 15;
+pub const MAX_CODE: u16 = 0xfff;
+
+/// Source of an event. Append only: the numbers are decoded by `xtask`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Subsystem {
+    Harness = 0x01,
+    Check = 0x02,
+    ResetValue = 0x03,
+    IrqSetup = 0x04,
+    Irq = 0x05,
+    Wfi = 0x06,
+    Trap = 0x07,
+    Satp = 0x08,
+    Cache = 0x09,
+    Ram = 0x0a,
+    Xip = 0x0b,
+    Rram = 0x0c,
+    Pio = 0x0d,
+    Init = 0x0e,
+    Console = 0x0f,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    Begin,
+    End,
+    /// Progress marker; the code is the step number
+    Step(u16),
+    /// A check passed; the code is the test index
+    Pass(u16),
+    /// A check failed; the code is the test index
+    Fail(u16),
+    /// Data for inspection; the code says what the payload is
+    Value(u16),
+}
+
+impl Event {
+    fn kind(self) -> u32 {
+        match self {
+            Event::Begin => 0,
+            Event::End => 1,
+            Event::Step(_) => 2,
+            Event::Pass(_) => 3,
+            Event::Fail(_) => 4,
+            Event::Value(_) => 5,
+        }
+    }
+
+    fn code(self) -> u16 {
+        match self {
+            Event::Begin | Event::End => 0,
+            Event::Step(c) | Event::Pass(c) | Event::Fail(c) | Event::Value(c) => c,
+        }
+    }
+}
+
+/// Encodes the tag word for `event` with `payload_len` payload words.
+pub fn tag(subsystem: Subsystem, event: Event, payload_len: usize) -> u32 {
+    assert!(payload_len <= MAX_PAYLOAD && event.code() <= MAX_CODE, "event does not fit in a tag");
+    TAG | event.kind() << 24 | (subsystem as u32) << 16 | (payload_len as u32) << 12 | event.code() as u32
+}
+
+impl Subsystem {
+    /// Writes `event` and its payload to the trace.
+    pub fn report(self, event: Event, payload: &[u32]) {
+        report_api(tag(self, event, payload.len()));
+        for &word in payload {
+            report_api(word);
+        }
+    }
+}
+
+/// `Subsystem::Harness` codes
+pub mod harness {
+    /// Value: the mask of enabled suites
+    pub const SELECTION: u16 = 1;
+}
+
+/// `Subsystem::Check` codes
+pub mod check {
+    /// Fail: `[address, actual, expected]` of a mismatching word; the address is 0 for a value
+    /// that was not read from memory
+    pub const MISMATCH: u16 = 1;
+    /// Value: `[mismatching words]`, at the end of a comparison that failed
+    pub const MISMATCHES: u16 = 2;
+}
+
+/// `Subsystem::ResetValue` codes
+pub mod reset_value {
+    /// Value: `[reset PC]`
+    pub const PC: u16 = 1;
+}
+
+/// `Subsystem::Trap` codes
+pub mod trap {
+    /// Value: `[scause]`
+    pub const SCAUSE: u16 = 1;
+    /// Value: `[sip]`, the pending external interrupts
+    pub const PENDING: u16 = 2;
+    /// Value: `[sepc, stval, sim]`
+    pub const CSRS: u16 = 3;
+    /// Value: `[sstatus]` on the way out
+    pub const STATUS: u16 = 4;
+    /// Step: PIO interrupt taken by the quantum timer test
+    pub const PIO_IRQ: u16 = 5;
+    /// Value: `[pending]` for IRQ bank `IRQ_BANK + n`
+    pub const IRQ_BANK: u16 = 0x100;
+}
+
+/// `Subsystem::Satp` codes
+pub mod satp {
+    /// Value: `[asid, entry]` from a coreuser ASID table readback
+    pub const ASID: u16 = 1;
+}
+
+/// `Subsystem::Cache` and `Subsystem::Ram` codes
+pub mod cache {
+    /// Value: `[address]` of a location about to be written
+    pub const ADDR: u16 = 1;
+    /// Value: `[bottom, top]` words read back from one cache set
+    pub const READ: u16 = 2;
+    /// Value: `[value]` read back from an uncached I/O register
+    pub const IO_READ: u16 = 3;
+    /// Step: first read pass, before the flush (`+ iteration`)
+    pub const PRE_FLUSH: u16 = 0x10;
+    /// Step: about to flush the cache
+    pub const FLUSH: u16 = 0x20;
+    /// Step: cache flushed
+    pub const FLUSHED: u16 = 0x21;
+    /// Step: second read pass, after the flush (`+ iteration`)
+    pub const POST_FLUSH: u16 = 0x30;
+    /// Step: start of the uncached I/O check
+    pub const IO: u16 = 0x40;
+    /// Fail: test slice is the wrong size for the test (`+ test index`)
+    pub const CONFIG: u16 = 0xf00;
+}
+
+/// `Subsystem::Xip` codes
+pub mod xip {
+    /// Value: `[result]` of a call into the XIP region
+    pub const RESULT: u16 = 1;
+}
+
+/// `Subsystem::Rram` codes
+pub mod rram {
+    /// Value: `[address, value]`
+    pub const READBACK: u16 = 1;
+    /// Value: `[control]` word of the DMA channel being polled
+    pub const DMA_CONTROL: u16 = 2;
+}
+
+/// `Subsystem::Pio` codes
+pub mod pio {
+    /// Step: start of one SPI loopback transfer
+    pub const TRANSFER: u16 = 0x10;
+    /// Value: `[byte]` sent over the SPI loopback
+    pub const TX: u16 = 1;
+    /// Fail: `[sent, received]`
+    pub const MISMATCH: u16 = 2;
+}
+
+/// `Subsystem::Init` codes
+pub mod init {
+    /// Value: `[divider]` for the UDMA UART baud rate
+    pub const UART_DIV: u16 = 1;
+}
+
+/// `Subsystem::Console` codes
+pub mod console {
+    /// Value: `[text]`, up to four bytes of console output packed little-endian; unused bytes are 0
+    pub const TEXT: u16 = 1;
+}
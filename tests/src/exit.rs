@@ -0,0 +1,82 @@
+// (c) Copyright CrossBar, Inc. 2024.
+//
+// This documentation describes Open Hardware and is licensed under the
+// [CERN-OHL-W-2.0].
+//
+// You may redistribute and modify this documentation under the terms of the
+// [CERN-OHL- W-2.0 (http://ohwr.org/cernohl)]. This documentation is
+// distributed WITHOUT ANY EXPRESS OR IMPLIED WARRANTY, INCLUDING OF
+// MERCHANTABILITY, SATISFACTORY QUALITY AND FITNESS FOR A PARTICULAR PURPOSE.
+// Please see the [CERN-OHL- W-2.0] for applicable conditions.
+
+//! End-of-run protocol, so that a testbench can tell how the run went without parsing the log.
+//!
+//! Every run, including one cut short by a panic, ends with these `csrtest` writes:
+//!
+//! | Word | Meaning |
+//! |------|---------|
+//! | `EXIT_TAG \| status` | overall `ExitStatus` in the low byte |
+//! | failure count | number of suites that failed; a panic that ends the run counts as one |
+//!
+//! followed by `main::DONE_DONE` and the `0xc0ded02e`, `0xc0de600d` end-of-simulation sequence.
+
+use utralib::generated::*;
+
+use crate::*;
+
+/// Marks the status word; the status is in the low byte
+pub const EXIT_TAG: u32 = 0xc0de_5700;
+
+/// Overall result of a run, most severe first. Don't renumber: testbenches map these to process
+/// exit codes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExitStatus {
+    Pass = 0,
+    Fail = 1,
+    Panic = 2,
+    Timeout = 3,
+}
+
+impl ExitStatus {
+    pub fn name(self) -> &'static str {
+        match self {
+            ExitStatus::Pass => "pass",
+            ExitStatus::Fail => "fail",
+            ExitStatus::Panic => "panic",
+            ExitStatus::Timeout => "timeout",
+        }
+    }
+}
+
+/// Works out the status of a completed run and the number of failed suites. A run in which a
+/// failing suite panicked is a panic, and otherwise one in which a failing suite timed out is a
+/// timeout; expected failures don't count.
+pub fn run_status(tests: &[&mut dyn Test]) -> (ExitStatus, u32) {
+    let failed = || tests.iter().filter(|t| t.outcome().is_failure());
+    let failures = failed().count() as u32;
+    let status = if failed().any(|t| t.stats().panic.is_some()) {
+        ExitStatus::Panic
+    } else if failed().any(|t| t.stats().timed_out) {
+        ExitStatus::Timeout
+    } else if failures != 0 {
+        ExitStatus::Fail
+    } else {
+        ExitStatus::Pass
+    };
+    (status, failures)
+}
+
+/// Reports the outcome of the run and ends the simulation.
+pub fn end_run(status: ExitStatus, failures: u32) -> ! {
+    println!("Run status: {}, {} failed", status.name(), failures);
+    let mut test_cfg = CSR::new(utra::csrtest::HW_CSRTEST_BASE as *mut u32);
+    test_cfg.wo(utra::csrtest::WTEST, EXIT_TAG | status as u32);
+    test_cfg.wo(utra::csrtest::WTEST, failures);
+    // this triggers the simulation to end using a sim-only verilog hook
+    let mut report = CSR::new(utra::main::HW_MAIN_BASE as *mut u32);
+    report.wfo(utra::main::DONE_DONE, 1);
+    // this sequence triggers an end of simulation on s32
+    test_cfg.wo(utra::csrtest::WTEST, 0xc0ded02e);
+    test_cfg.wo(utra::csrtest::WTEST, 0xc0de600d);
+    loop {}
+}
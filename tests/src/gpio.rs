@@ -17,7 +17,7 @@ use crate::println;
 use crate::{TestBoilerplate, TestRunner};
 
 const TOTAL_TESTS: usize = 1;
-crate::impl_test!(GpioTests, "GPIO", TOTAL_TESTS);
+crate::impl_test!(GpioTests, "GPIO", TOTAL_TESTS, Gpio);
 
 #[allow(dead_code)]
 pub const TEST_I2C_MASK: u32 = 0b00001;
@@ -67,21 +67,13 @@ impl TestRunner for GpioTests {
             let pb = iox.get_gpio_bank(IoxPort::PB);
             let pc = iox.get_gpio_bank(IoxPort::PC);
             println!("PB: {:x}, PC: {:x}", pb, pc);
-            if pb != val {
-                passing = false;
-            }
-            if pc != !val {
-                passing = false;
-            }
+            passing &= crate::check::diff_values("PB", pb, val);
+            passing &= crate::check::diff_values("PC", pc, !val);
         }
 
         // reset to PIO routed ports
         test_cfg.wo(utra::csrtest::WTEST, 0);
         iox.set_ports_from_pio_bitmask(0xFFFF_FFFF);
-        if passing {
-            self.passing_tests = 1;
-        } else {
-            self.passing_tests = 0;
-        }
+        self.cases.check("force_readback", passing, "PB/PC did not follow forced values");
     }
 }
@@ -0,0 +1,852 @@
+// (c) Copyright CrossBar, Inc. 2024.
+//
+// This documentation describes Open Hardware and is licensed under the
+// [CERN-OHL-W-2.0].
+//
+// You may redistribute and modify this documentation under the terms of the
+// [CERN-OHL- W-2.0 (http://ohwr.org/cernohl)]. This documentation is
+// distributed WITHOUT ANY EXPRESS OR IMPLIED WARRANTY, INCLUDING OF
+// MERCHANTABILITY, SATISFACTORY QUALITY AND FITNESS FOR A PARTICULAR PURPOSE.
+// Please see the [CERN-OHL- W-2.0] for applicable conditions.
+
+//! Runtime machinery for sequencing the test suites registered in `rust_entry`.
+//!
+//! There is no .data/.bss initialization in this environment, so all harness state lives
+//! at fixed addresses in SRAM. The region is documented in `link.x`; it sits well above
+//! the page tables and RAM test areas at the base of SRAM, and well below the stack.
+
+use utralib::generated::*;
+
+use crate::event::{self, Event, Subsystem};
+use crate::*;
+
+/// Base of the SRAM region reserved for harness bookkeeping.
+pub const HARNESS_BASE: usize = 0x6110_0000;
+/// Suite selection table. A testbench preloads this (or a debugger pokes it before a soft reset)
+/// to pick which suites run, without rebuilding the image.
+pub const SELECT_TABLE: usize = HARNESS_BASE;
+
+/// Marks the selection table as valid. Anything else at `SELECT_TABLE` means "use the defaults".
+pub const SELECT_MAGIC: u32 = 0x5e1e_c7ed;
+/// Harness state that must survive a suite being abandoned, and that a post-mortem SRAM dump can
+/// recover. Initialized by `init()` on every boot.
+pub const STATE: usize = HARNESS_BASE + 0x40;
+
+/// `SelectTable::flags`: end the run at the first watchdog timeout instead of moving on
+pub const FLAG_STOP_ON_TIMEOUT: u32 = 1 << 0;
+/// `SelectTable::flags`: end a soak at the first iteration in which a suite fails
+pub const FLAG_SOAK_STOP_ON_FAIL: u32 = 1 << 1;
+/// `SelectTable::flags`: list the time taken by each case in the summary, not just each suite
+pub const FLAG_CASE_TIMING: u32 = 1 << 2;
+/// `SelectTable::flags`: end the run at the first panic instead of moving on to the next suite
+pub const FLAG_HALT_ON_PANIC: u32 = 1 << 3;
+/// `SelectTable::flags`: enter the serial console (see `console.rs`) after the normal run
+pub const FLAG_CONSOLE: u32 = 1 << 4;
+/// `SelectTable::flags`: buffer `print!` output during every suite, not just the timing-sensitive
+/// ones (see `logbuf.rs`)
+pub const FLAG_BUFFERED_LOG: u32 = 1 << 5;
+/// `SelectTable::flags`: also write console output to `csrtest` (see `tracebuf.rs`)
+pub const FLAG_LOG_CSRTEST: u32 = 1 << 6;
+
+/// Stable bit positions for each suite in the selection mask. Do not renumber these: masks
+/// are stored in testbench configs and regression scripts.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum SuiteId {
+    ResetValue = 0,
+    Aes = 1,
+    Bio = 2,
+    Gpio = 3,
+    SatpSetup = 4,
+    IrqSetup = 5,
+    Satp = 6,
+    Irq = 7,
+    Wfi = 8,
+    Ram = 9,
+    Timer0 = 10,
+    Mbox = 11,
+    Rram = 12,
+    RramDisturb = 13,
+    RramLifecycle = 14,
+    Udma = 15,
+    Cam = 16,
+    SetupUart2 = 17,
+    PioQuick = 18,
+    Pio = 19,
+    ByteStrobe = 20,
+    Xip = 21,
+    SceDma = 22,
+    Pl230 = 23,
+}
+
+impl SuiteId {
+    pub fn mask(self) -> u32 { 1 << (self as u32) }
+}
+
+/// Layout of the selection table at `SELECT_TABLE`.
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct SelectTable {
+    /// Must be `SELECT_MAGIC` for the table to be honored
+    pub magic: u32,
+    /// Bit `n` enables the suite with `SuiteId` `n`
+    pub mask: u32,
+    /// `FLAG_*` run options
+    pub flags: u32,
+    /// Suites to repeat after the normal run, as a `SuiteId` mask; 0 disables soak mode
+    pub soak_mask: u32,
+    /// Number of soak iterations; 0 repeats until another stop condition is met
+    pub soak_iterations: u32,
+    /// Soak time limit in ticktimer ticks; 0 for no limit
+    pub soak_ticks: u32,
+    /// Watchdog budget in ticktimer ticks for every suite that has one, in place of its own
+    /// `TestRunner::budget_ticks()`; 0 keeps each suite's budget
+    pub budget_ticks: u32,
+}
+
+/// Returns the selection table, if one has been loaded.
+pub fn select_table() -> Option<SelectTable> {
+    let table = unsafe { (SELECT_TABLE as *const SelectTable).read_volatile() };
+    if table.magic == SELECT_MAGIC { Some(table) } else { None }
+}
+
+/// Returns the run options from the selection table; none are set if there is no table.
+pub fn select_flags() -> u32 { select_table().map(|t| t.flags).unwrap_or(0) }
+
+/// Returns the watchdog budget for `test`: the selection table's override if it has one, unless
+/// the suite opts out of the watchdog altogether.
+pub fn budget_ticks(test: &dyn Test) -> u32 {
+    match test.budget_ticks() {
+        0 => 0,
+        own => select_table().map(|t| t.budget_ticks).filter(|&b| b != 0).unwrap_or(own),
+    }
+}
+
+/// Mask of the suites that are currently enabled.
+pub fn enabled_mask(tests: &[&mut dyn Test]) -> u32 {
+    tests.iter().filter(|t| t.is_enabled()).fold(0, |mask, t| mask | t.id().mask())
+}
+
+/// Applies the runtime selection to the registered suites. The cargo features used to
+/// construct each suite only set the default mask; a valid selection table overrides it.
+pub fn apply_selection(tests: &mut [&mut dyn Test]) {
+    let default = enabled_mask(tests);
+    if let Some(table) = select_table() {
+        crate::println!("Selection table: mask {:08x} (default {:08x})", table.mask, default);
+        if table.budget_ticks != 0 {
+            crate::println!("Watchdog budget: {} ticks", table.budget_ticks);
+            // the watchdog needs the trap handler that IrqSetup installs
+            if table.mask & SuiteId::IrqSetup.mask() == 0 {
+                crate::println!(
+                    "warning: IrqSetup is not selected, so only suites that require it run under the watchdog"
+                );
+            }
+        }
+        for test in tests.iter_mut() {
+            test.set_enable(table.mask & test.id().mask() != 0);
+        }
+    } else {
+        crate::println!("Default selection: mask {:08x}", default);
+    }
+    Subsystem::Harness.report(Event::Value(event::harness::SELECTION), &[enabled_mask(tests)]);
+}
+
+/// Upper bound on the number of suites registered in `rust_entry`; one per `SuiteId` bit.
+pub const MAX_SUITES: usize = 32;
+
+/// Enables the suites required by every enabled suite, transitively.
+pub fn enable_prerequisites(tests: &mut [&mut dyn Test]) {
+    loop {
+        let needed = tests.iter().filter(|t| t.is_enabled()).fold(0, |mask, t| mask | t.requires());
+        let mut changed = false;
+        for test in tests.iter_mut() {
+            if !test.is_enabled() && (needed & test.id().mask()) != 0 {
+                crate::println!("Enabling {} as a prerequisite", test.name());
+                test.set_enable(true);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+}
+
+/// Orders the enabled suites so that prerequisites run first. Otherwise suites keep their
+/// registration order. Returns indices into `tests`, and the number of valid entries.
+pub fn schedule(tests: &[&mut dyn Test]) -> ([usize; MAX_SUITES], usize) {
+    let mut order = [0usize; MAX_SUITES];
+    let mut count = 0;
+    let mut scheduled = [false; MAX_SUITES];
+    let enabled = enabled_mask(tests);
+    let mut placed = 0u32;
+    loop {
+        // take the first suite in registration order whose prerequisites have all been placed;
+        // prerequisites that are not enabled are caught later by `blocking_prerequisite()`
+        let ready = tests
+            .iter()
+            .enumerate()
+            .find(|(i, t)| !scheduled[*i] && t.is_enabled() && (t.requires() & enabled & !placed) == 0);
+        match ready {
+            Some((i, t)) => {
+                order[count] = i;
+                count += 1;
+                scheduled[i] = true;
+                placed |= t.id().mask();
+            }
+            None => break,
+        }
+    }
+    for (i, t) in tests.iter().enumerate() {
+        if t.is_enabled() && !scheduled[i] {
+            crate::println!("Dependency cycle involving {}", t.name());
+            order[count] = i;
+            count += 1;
+        }
+    }
+    (order, count)
+}
+
+/// Returns the name of the first prerequisite in `requires` that has not passed, if any.
+pub fn blocking_prerequisite(tests: &[&mut dyn Test], requires: u32, passed: u32) -> Option<&'static str> {
+    let missing = requires & !passed;
+    if missing == 0 {
+        return None;
+    }
+    Some(
+        tests
+            .iter()
+            .find(|t| (missing & t.id().mask()) != 0)
+            .map(|t| t.name())
+            .unwrap_or("unregistered suite"),
+    )
+}
+
+/// Runs the enabled suites in dependency order. A suite whose prerequisite failed is not run,
+/// and is recorded as failed so that anything depending on it is skipped too.
+///
+/// Once `IrqSetup` has passed, each suite runs under a ticktimer watchdog. A suite that overruns
+/// its budget (see `budget_ticks()`) is abandoned and recorded as timed out, and the run moves
+/// on to the next suite (or stops, if `FLAG_STOP_ON_TIMEOUT` is set). Suites that run before
+/// the trap handler is installed are not guarded; `HarnessState::current` still records which
+/// suite was running for a post-mortem dump.
+///
+/// A suite that panics is abandoned in the same way, through `panic_resume()`, unless
+/// `FLAG_HALT_ON_PANIC` is set.
+///
+/// Returns the mask of the suites that passed.
+pub fn run_suites(tests: &mut [&mut dyn Test]) -> u32 {
+    enable_prerequisites(tests);
+    let (order, count) = schedule(tests);
+    let mut passed = 0u32;
+    for &i in order[..count].iter() {
+        if let Some(prereq) = blocking_prerequisite(tests, tests[i].requires(), passed) {
+            crate::println!("!!! Not running {}: prerequisite {} did not pass", tests[i].name(), prereq);
+            tests[i].cases_mut().fail("prerequisite", prereq);
+            continue;
+        }
+        let test = &mut tests[i];
+        crate::println!(">>> Running {}", test.name());
+        match run_timed(&mut **test, passed) {
+            Exit::Returned => crate::println!("<<< {} done", test.name()),
+            Exit::Timeout => {
+                if (select_flags() & FLAG_STOP_ON_TIMEOUT) != 0 {
+                    crate::println!("Run ended: TIMEOUT");
+                    break;
+                }
+            }
+            Exit::Panic => (),
+        }
+        if test.outcome().is_pass() {
+            passed |= test.id().mask();
+        }
+    }
+    passed
+}
+
+/// Runs one suite and records how long it took. `passed` is the mask of suites that have passed
+/// so far; the watchdog is only armed once `IrqSetup` is among them. A timeout or panic is
+/// recorded as a failed case.
+pub fn run_timed(test: &mut dyn Test, passed: u32) -> Exit {
+    unsafe { core::ptr::addr_of_mut!((*state()).passed).write_volatile(passed) };
+    let budget = if (passed & SuiteId::IrqSetup.mask()) != 0 { budget_ticks(test) } else { 0 };
+    let buffered = test.buffered_log() || (select_flags() & FLAG_BUFFERED_LOG) != 0;
+    if buffered {
+        crate::logbuf::set_buffered(true);
+    }
+    let start_ticks = now_ticks();
+    let start_cycles = now_cycles();
+    test.cases_mut().start();
+    let exit = run_guarded(test, budget);
+    let cycles = now_cycles().wrapping_sub(start_cycles);
+    let stats = test.stats_mut();
+    stats.ran = true;
+    stats.start_ticks = start_ticks;
+    stats.end_ticks = now_ticks();
+    stats.cycles = cycles;
+    // drain outside of the suite's timing
+    if buffered {
+        crate::logbuf::set_buffered(false);
+    }
+    match exit {
+        Exit::Returned => (),
+        Exit::Timeout => {
+            let report = unsafe { core::ptr::addr_of!((*state()).timeout_report).read_volatile() };
+            crate::println!(
+                "!!! TIMEOUT in {} after {} ticks, last report {:08x}",
+                test.name(),
+                budget,
+                report
+            );
+            test.stats_mut().timed_out = true;
+            test.cases_mut().fail("watchdog", "suite timed out");
+        }
+        Exit::Panic => {
+            let location = panic_location();
+            crate::println!("!!! PANIC in {} at {}:{}", test.name(), location.0, location.1);
+            test.stats_mut().panic = Some(location);
+            test.cases_mut().fail("panic", "suite panicked");
+        }
+    }
+    if test.outcome().is_failure() {
+        unsafe {
+            let failed = core::ptr::addr_of!((*state()).failed).read_volatile();
+            core::ptr::addr_of_mut!((*state()).failed).write_volatile(failed | test.id().mask());
+        }
+    }
+    exit
+}
+
+/// Per-suite run statistics, kept alongside the case log
+#[derive(Copy, Clone, Debug)]
+pub struct SuiteStats {
+    /// Set once the suite has been started, even if it was later abandoned
+    pub ran: bool,
+    pub start_ticks: u64,
+    pub end_ticks: u64,
+    /// CPU cycles spent in the suite
+    pub cycles: u64,
+    /// File and line at which the suite panicked, if it did
+    pub panic: Option<(&'static str, u32)>,
+    /// Set if the suite was abandoned by the watchdog
+    pub timed_out: bool,
+}
+
+impl SuiteStats {
+    pub const fn new() -> Self {
+        SuiteStats { ran: false, start_ticks: 0, end_ticks: 0, cycles: 0, panic: None, timed_out: false }
+    }
+
+    /// Ticks spent in the suite. Suites that reset the ticktimer (e.g. `WfiTests`) report 0.
+    pub fn elapsed_ticks(&self) -> u64 { self.end_ticks.saturating_sub(self.start_ticks) }
+}
+
+/// Value of `HarnessState::current` when no suite is running
+pub const NO_SUITE: u32 = 0xFFFF_FFFF;
+/// Default watchdog budget for a suite, in ticktimer ticks. A tick is about 1ms of simulated time
+/// at the default tick rate, however fast the simulator runs, so the budget means the same in RTL
+/// simulation as on silicon. Suites that legitimately run longer override
+/// `TestRunner::budget_ticks()`; a tighter bound for a whole run goes in the selection table.
+pub const DEFAULT_BUDGET_TICKS: u32 = 5_000;
+
+/// Why a guarded suite stopped running
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum Exit {
+    Returned = 0,
+    Timeout = 1,
+    Panic = 2,
+}
+
+#[repr(C)]
+pub struct HarnessState {
+    /// `SuiteId` of the suite currently running, or `NO_SUITE`
+    pub current: u32,
+    /// Last word written through `report_api`
+    pub last_report: u32,
+    /// `last_report` as it was when the watchdog last fired
+    pub timeout_report: u32,
+    /// Watchdog deadline in ticktimer ticks, or 0 when disarmed
+    pub deadline_lo: u32,
+    pub deadline_hi: u32,
+    /// Mask of the suites that had passed when the current suite started
+    pub passed: u32,
+    /// Location of the last panic: a pointer to and length of the file name, and the line
+    pub panic_file: u32,
+    pub panic_file_len: u32,
+    pub panic_line: u32,
+    /// Callee-saved registers at the point the current suite was entered; see `asm.rs`
+    pub checkpoint: [u32; 14],
+    /// Mask of the suites that have failed, for the exit status of a run that ends in a panic. A
+    /// mask rather than a count, as soak mode and the console can run a failed suite again.
+    pub failed: u32,
+}
+
+fn state() -> *mut HarnessState { STATE as *mut HarnessState }
+
+/// Resets the harness state. SRAM is not initialized at boot, so this must run before anything
+/// that can panic: `panic_resume()` and `failures()` read the state unconditionally.
+pub fn init() {
+    crate::logbuf::init();
+    crate::results::clear();
+    unsafe {
+        (*state()).current = NO_SUITE;
+        (*state()).last_report = 0;
+        (*state()).timeout_report = 0;
+        (*state()).deadline_lo = 0;
+        (*state()).deadline_hi = 0;
+        (*state()).passed = 0;
+        (*state()).panic_file = 0;
+        (*state()).panic_file_len = 0;
+        (*state()).panic_line = 0;
+        (*state()).failed = 0;
+    }
+}
+
+/// Called by `report_api` so the watchdog can say where a hung suite got to.
+pub fn note_report(d: u32) { unsafe { core::ptr::addr_of_mut!((*state()).last_report).write_volatile(d) } }
+
+pub fn last_report() -> u32 { unsafe { core::ptr::addr_of!((*state()).last_report).read_volatile() } }
+
+/// Number of suites that have failed so far; see `HarnessState::failed`.
+pub fn failures() -> u32 { unsafe { core::ptr::addr_of!((*state()).failed).read_volatile().count_ones() } }
+
+pub fn now_ticks() -> u64 {
+    let tt = CSR::new(utra::ticktimer::HW_TICKTIMER_BASE as *mut u32);
+    loop {
+        let hi = tt.r(utra::ticktimer::TIME1);
+        let lo = tt.r(utra::ticktimer::TIME0);
+        if hi == tt.r(utra::ticktimer::TIME1) {
+            return (hi as u64) << 32 | lo as u64;
+        }
+    }
+}
+
+/// Reads the `cycle` CSR, the read-only shadow of `mcycle`. Unlike `mcycle` it is also readable
+/// in supervisor mode, since `satp_setup()` opens it up through `mcounteren`.
+pub fn now_cycles() -> u64 {
+    loop {
+        let hi: u32;
+        let lo: u32;
+        let hi2: u32;
+        unsafe {
+            core::arch::asm!(
+                "csrr {hi}, cycleh",
+                "csrr {lo}, cycle",
+                "csrr {hi2}, cycleh",
+                hi = out(reg) hi,
+                lo = out(reg) lo,
+                hi2 = out(reg) hi2,
+            );
+        }
+        if hi == hi2 {
+            return (hi as u64) << 32 | lo as u64;
+        }
+    }
+}
+
+/// Arms the ticktimer alarm to fire `budget` ticks from now. Only meaningful once the supervisor
+/// trap handler is installed by `IrqSetup`.
+fn watchdog_arm(budget: u32) {
+    let deadline = now_ticks() + budget as u64;
+    unsafe {
+        core::ptr::addr_of_mut!((*state()).deadline_hi).write_volatile((deadline >> 32) as u32);
+        core::ptr::addr_of_mut!((*state()).deadline_lo).write_volatile(deadline as u32);
+    }
+    let mut tt = CSR::new(utra::ticktimer::HW_TICKTIMER_BASE as *mut u32);
+    tt.wo(utra::ticktimer::MSLEEP_TARGET1, (deadline >> 32) as u32);
+    tt.wo(utra::ticktimer::MSLEEP_TARGET0, deadline as u32);
+    tt.wfo(utra::ticktimer::EV_PENDING_ALARM, 1);
+    tt.wfo(utra::ticktimer::EV_ENABLE_ALARM, 1);
+}
+
+fn watchdog_disarm() {
+    let mut tt = CSR::new(utra::ticktimer::HW_TICKTIMER_BASE as *mut u32);
+    tt.wfo(utra::ticktimer::EV_ENABLE_ALARM, 0);
+    unsafe {
+        core::ptr::addr_of_mut!((*state()).deadline_lo).write_volatile(0);
+        core::ptr::addr_of_mut!((*state()).deadline_hi).write_volatile(0);
+    }
+}
+
+/// Called from the trap handler on a ticktimer interrupt. If the current suite has overrun its
+/// budget, rewrites the trap frame at `frame` so that the `sret` lands back in `run_guarded()`
+/// instead of resuming the suite.
+pub fn watchdog_check(frame: *mut u32, last_report: u32) {
+    let deadline = unsafe {
+        (core::ptr::addr_of!((*state()).deadline_hi).read_volatile() as u64) << 32
+            | core::ptr::addr_of!((*state()).deadline_lo).read_volatile() as u64
+    };
+    if deadline == 0 || now_ticks() < deadline {
+        return;
+    }
+    watchdog_disarm();
+    unsafe {
+        core::ptr::addr_of_mut!((*state()).timeout_report).write_volatile(last_report);
+        abandon_suite(frame, Exit::Timeout);
+    }
+}
+
+/// Called from the panic handler. If a suite is running, abandons it and resumes the harness at
+/// the suite's checkpoint; the harness then records the panic and carries on with the next suite.
+/// The checkpoint restores the harness's stack, and if the trap handler is installed, interrupts
+/// are re-enabled as on the way out of a trap, in case the panic happened inside the handler.
+///
+/// Returns if no suite is running or `FLAG_HALT_ON_PANIC` is set, in which case the caller
+/// should end the run.
+pub fn panic_resume(location: Option<&core::panic::Location>) {
+    unsafe {
+        if core::ptr::addr_of!((*state()).current).read_volatile() == NO_SUITE
+            || (select_flags() & FLAG_HALT_ON_PANIC) != 0
+        {
+            return;
+        }
+        // a second panic while recovering must not come back here
+        core::ptr::addr_of_mut!((*state()).current).write_volatile(NO_SUITE);
+        watchdog_disarm();
+        let (file, line) = location.map(|l| (l.file(), l.line())).unwrap_or(("<unknown>", 0));
+        core::ptr::addr_of_mut!((*state()).panic_file).write_volatile(file.as_ptr() as u32);
+        core::ptr::addr_of_mut!((*state()).panic_file_len).write_volatile(file.len() as u32);
+        core::ptr::addr_of_mut!((*state()).panic_line).write_volatile(line);
+        if (core::ptr::addr_of!((*state()).passed).read_volatile() & SuiteId::IrqSetup.mask()) != 0 {
+            crate::irqs::reenable_irqs();
+        }
+        crate::asm::harness_resume(
+            core::ptr::addr_of!((*state()).checkpoint) as *const u32,
+            Exit::Panic as u32,
+        );
+    }
+}
+
+/// Returns the location saved by `panic_resume()`.
+fn panic_location() -> (&'static str, u32) {
+    unsafe {
+        let file = core::ptr::addr_of!((*state()).panic_file).read_volatile() as *const u8;
+        let len = core::ptr::addr_of!((*state()).panic_file_len).read_volatile() as usize;
+        let line = core::ptr::addr_of!((*state()).panic_line).read_volatile();
+        // panic locations are string literals in .rodata, so the file name lives forever
+        let file = core::str::from_utf8(core::slice::from_raw_parts(file, len)).unwrap_or("<unknown>");
+        (file, line)
+    }
+}
+
+/// Points the trap frame's return at `harness_resume` with the suite checkpoint as arguments.
+/// The frame layout is the one saved by `_start_trap_aligned`: slot n holds x(n+1), slot 31 sepc.
+unsafe fn abandon_suite(frame: *mut u32, exit: Exit) {
+    frame.add(9).write_volatile(core::ptr::addr_of!((*state()).checkpoint) as u32); // a0
+    frame.add(10).write_volatile(exit as u32); // a1
+    frame.add(31).write_volatile(crate::asm::harness_resume as *const () as u32); // sepc
+    // the harness runs in supervisor mode, whatever mode the suite was left in
+    #[rustfmt::skip]
+    core::arch::asm!(
+        "li          {tmp}, 0x100", // sstatus.SPP
+        "csrs        sstatus, {tmp}",
+        tmp = out(reg) _,
+    );
+}
+
+/// Runs one suite under the watchdog, if `budget` is non-zero. Kept out of line and free of
+/// state after the checkpoint, because it returns a second time when a suite is abandoned.
+#[inline(never)]
+fn run_guarded(test: &mut dyn Test, budget: u32) -> Exit {
+    unsafe {
+        core::ptr::addr_of_mut!((*state()).current).write_volatile(test.id() as u32);
+        let exit = crate::asm::harness_checkpoint(core::ptr::addr_of_mut!((*state()).checkpoint) as *mut u32);
+        if exit != 0 {
+            core::ptr::addr_of_mut!((*state()).current).write_volatile(NO_SUITE);
+            return if exit == Exit::Panic as u32 { Exit::Panic } else { Exit::Timeout };
+        }
+        if budget != 0 {
+            watchdog_arm(budget);
+        }
+        test.run();
+        if budget != 0 {
+            watchdog_disarm();
+        }
+        core::ptr::addr_of_mut!((*state()).current).write_volatile(NO_SUITE);
+    }
+    Exit::Returned
+}
+
+/// Maximum number of named cases a suite can record. Cases beyond this still count towards
+/// the suite's pass totals, but their names are not retained.
+pub const MAX_CASES: usize = 24;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Pass,
+    Fail,
+    Skip,
+    /// A failure in a suite that is declared as known-broken
+    ExpectedFail,
+}
+
+/// What a suite is declared to do, independent of how it actually fares
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Expectation {
+    Pass,
+    /// The suite is not run by default (e.g. legacy tests); the reason is reported in its place
+    Skip(&'static str),
+    /// The suite is known to fail; its failures are reported as expected rather than as failures
+    Fail(&'static str),
+}
+
+/// Overall outcome of a suite, reported separately from the plain pass/fail counts
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SuiteOutcome {
+    /// Not selected, and not declared as skipped
+    NotRun,
+    Passed,
+    Failed,
+    Skipped(&'static str),
+    ExpectedFail(&'static str),
+    /// A suite declared as known-broken passed; the declaration is probably stale
+    UnexpectedPass(&'static str),
+}
+
+impl SuiteOutcome {
+    /// Whether the suite did what it was meant to, so that suites requiring it may run
+    pub fn is_pass(&self) -> bool { matches!(self, SuiteOutcome::Passed | SuiteOutcome::UnexpectedPass(_)) }
+
+    /// Whether the outcome should fail the run
+    pub fn is_failure(&self) -> bool { *self == SuiteOutcome::Failed }
+
+    /// Short tag used in summaries and the result record
+    pub fn tag(&self) -> &'static str {
+        match self {
+            SuiteOutcome::NotRun => "notrun",
+            SuiteOutcome::Passed => "pass",
+            SuiteOutcome::Failed => "fail",
+            SuiteOutcome::Skipped(_) => "skip",
+            SuiteOutcome::ExpectedFail(_) => "xfail",
+            SuiteOutcome::UnexpectedPass(_) => "xpass",
+        }
+    }
+
+    /// Numeric form of `tag()`, for the SRAM results mailbox
+    pub fn code(&self) -> u32 {
+        match self {
+            SuiteOutcome::NotRun => 0,
+            SuiteOutcome::Passed => 1,
+            SuiteOutcome::Failed => 2,
+            SuiteOutcome::Skipped(_) => 3,
+            SuiteOutcome::ExpectedFail(_) => 4,
+            SuiteOutcome::UnexpectedPass(_) => 5,
+        }
+    }
+
+    pub fn reason(&self) -> Option<&'static str> {
+        match self {
+            SuiteOutcome::Skipped(r) | SuiteOutcome::ExpectedFail(r) | SuiteOutcome::UnexpectedPass(r) => {
+                Some(r)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Result of one named sub-case within a suite. A case may aggregate several checks,
+/// in which case `passed`/`total` give the breakdown.
+#[derive(Copy, Clone, Debug)]
+pub struct CaseResult {
+    pub name: &'static str,
+    pub outcome: Outcome,
+    /// Failure message, or the reason a case was skipped
+    pub msg: &'static str,
+    pub passed: u16,
+    pub total: u16,
+    /// Ticks and cycles since the previous case was recorded, or since the suite started
+    pub ticks: u32,
+    pub cycles: u64,
+}
+
+impl CaseResult {
+    const EMPTY: CaseResult =
+        CaseResult { name: "", outcome: Outcome::Skip, msg: "", passed: 0, total: 0, ticks: 0, cycles: 0 };
+}
+
+/// Per-suite registry of case results. This is the source of truth for a suite's pass count.
+pub struct CaseLog {
+    cases: [CaseResult; MAX_CASES],
+    len: usize,
+    dropped: usize,
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+    expected_failures: usize,
+    /// Checks that were not made, or that failed as expected; these don't count against the suite
+    excused: usize,
+    expect: Expectation,
+    /// Time at which the current case started, for per-case timing
+    mark_ticks: u64,
+    mark_cycles: u64,
+}
+
+impl CaseLog {
+    pub const fn new() -> Self {
+        CaseLog {
+            cases: [CaseResult::EMPTY; MAX_CASES],
+            len: 0,
+            dropped: 0,
+            passed: 0,
+            failed: 0,
+            skipped: 0,
+            expected_failures: 0,
+            excused: 0,
+            expect: Expectation::Pass,
+            mark_ticks: 0,
+            mark_cycles: 0,
+        }
+    }
+
+    /// Marks the start of the suite, so that the first case's time is measured from here.
+    pub fn start(&mut self) {
+        self.mark_ticks = now_ticks();
+        self.mark_cycles = now_cycles();
+    }
+
+    /// Forgets all recorded cases, keeping the expectation. Used to rerun a suite.
+    pub fn clear(&mut self) { *self = CaseLog { expect: self.expect, ..CaseLog::new() } }
+
+    /// Declares what the suite is expected to do. Must be called before any case is recorded.
+    pub fn set_expectation(&mut self, expect: Expectation) { self.expect = expect }
+
+    pub fn expectation(&self) -> Expectation { self.expect }
+
+    pub fn record(
+        &mut self,
+        name: &'static str,
+        outcome: Outcome,
+        msg: &'static str,
+        passed: usize,
+        total: usize,
+    ) {
+        let outcome = match (outcome, self.expect) {
+            (Outcome::Fail, Expectation::Fail(_)) => Outcome::ExpectedFail,
+            (outcome, _) => outcome,
+        };
+        match outcome {
+            Outcome::Fail => {
+                crate::println!("  case {} FAILED: {} ({}/{})", name, msg, passed, total);
+                self.failed += 1;
+            }
+            Outcome::Skip => {
+                crate::println!("  case {} skipped: {}", name, msg);
+                self.skipped += 1;
+                self.excused += total.saturating_sub(passed);
+            }
+            Outcome::ExpectedFail => {
+                crate::println!("  case {} failed as expected: {} ({}/{})", name, msg, passed, total);
+                self.expected_failures += 1;
+                self.excused += total.saturating_sub(passed);
+            }
+            Outcome::Pass => (),
+        }
+        self.passed += passed;
+        let (ticks, cycles) = (now_ticks(), now_cycles());
+        let elapsed_ticks = ticks.saturating_sub(self.mark_ticks) as u32;
+        let elapsed_cycles = cycles.wrapping_sub(self.mark_cycles);
+        self.mark_ticks = ticks;
+        self.mark_cycles = cycles;
+        if self.len < MAX_CASES {
+            self.cases[self.len] = CaseResult {
+                name,
+                outcome,
+                msg,
+                passed: passed as u16,
+                total: total as u16,
+                ticks: elapsed_ticks,
+                cycles: elapsed_cycles,
+            };
+            self.len += 1;
+        } else {
+            self.dropped += 1;
+        }
+    }
+
+    pub fn pass(&mut self, name: &'static str) { self.record(name, Outcome::Pass, "", 1, 1) }
+
+    pub fn fail(&mut self, name: &'static str, msg: &'static str) {
+        self.record(name, Outcome::Fail, msg, 0, 1)
+    }
+
+    /// Records a check that was not made. It counts towards neither the passes nor the failures.
+    pub fn skip(&mut self, name: &'static str, reason: &'static str) {
+        self.record(name, Outcome::Skip, reason, 0, 1)
+    }
+
+    /// Records a single pass/fail check, returning `ok` so callers can branch on it.
+    pub fn check(&mut self, name: &'static str, ok: bool, msg: &'static str) -> bool {
+        if ok {
+            self.pass(name)
+        } else {
+            self.fail(name, msg)
+        }
+        ok
+    }
+
+    /// Records a case made of `total` checks, of which `passed` succeeded. This is the adapter for
+    /// the many test routines that return a count of passing checks.
+    pub fn tally(&mut self, name: &'static str, passed: usize, total: usize) -> usize {
+        let outcome = if passed >= total { Outcome::Pass } else { Outcome::Fail };
+        self.record(name, outcome, "not all checks passed", passed, total);
+        passed
+    }
+
+    /// Sum of passing checks over all recorded cases
+    pub fn passed(&self) -> usize { self.passed }
+
+    /// Number of failed cases, including any that were not itemized
+    pub fn failed(&self) -> usize { self.failed }
+
+    /// Number of skipped cases, including any that were not itemized
+    pub fn skipped(&self) -> usize { self.skipped }
+
+    /// Number of cases that failed as expected, including any that were not itemized
+    pub fn failed_as_expected(&self) -> usize { self.expected_failures }
+
+    /// Derives the suite's outcome from its cases. `ran` is whether the suite was started, and
+    /// `total` is the number of checks it is expected to make.
+    pub fn outcome(&self, ran: bool, total: usize) -> SuiteOutcome {
+        if self.failed != 0 {
+            SuiteOutcome::Failed
+        } else if !ran {
+            match self.expect {
+                Expectation::Skip(reason) => SuiteOutcome::Skipped(reason),
+                _ => SuiteOutcome::NotRun,
+            }
+        } else if self.passed + self.excused < total {
+            // the suite returned without making all of its checks
+            SuiteOutcome::Failed
+        } else if let Expectation::Fail(reason) = self.expect {
+            if self.expected_failures != 0 {
+                SuiteOutcome::ExpectedFail(reason)
+            } else {
+                SuiteOutcome::UnexpectedPass(reason)
+            }
+        } else if self.passed == 0 && self.skipped != 0 {
+            let reason = self.iter().find(|c| c.outcome == Outcome::Skip).map(|c| c.msg);
+            SuiteOutcome::Skipped(reason.unwrap_or("all cases skipped"))
+        } else {
+            SuiteOutcome::Passed
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &CaseResult> { self.cases[..self.len].iter() }
+
+    pub fn failures(&self) -> impl Iterator<Item = &CaseResult> {
+        self.iter().filter(|c| c.outcome == Outcome::Fail)
+    }
+
+    pub fn expected_failures(&self) -> impl Iterator<Item = &CaseResult> {
+        self.iter().filter(|c| c.outcome == Outcome::ExpectedFail)
+    }
+
+    /// Failed cases along with their case ID, which is the order in which they were recorded
+    pub fn failure_ids(&self) -> impl Iterator<Item = (usize, &CaseResult)> {
+        self.iter().enumerate().filter(|(_, c)| c.outcome == Outcome::Fail)
+    }
+
+    /// Number of cases whose names did not fit in the registry
+    pub fn dropped(&self) -> usize { self.dropped }
+}
@@ -13,11 +13,21 @@ use utralib::generated::*;
 use utralib::utra::sysctrl;
 
 use crate::debug;
-use crate::utils::*;
+use crate::event::{self, Event, Subsystem};
 use crate::*;
 
+/// Input clock of the UDMA UART that its baud divisor is based on. The clock setup in `rust_entry`
+/// doesn't derive it, so this is the fixed rate that the divisor has always assumed.
+pub const DEFAULT_PERCLK_HZ: u32 = 45_882_000;
+
 const SETUP_UART2_TESTS: usize = 0;
-crate::impl_test!(SetupUart2Tests, "Setup UART2", SETUP_UART2_TESTS);
+crate::impl_test!(
+    SetupUart2Tests,
+    "Setup UART2",
+    SETUP_UART2_TESTS,
+    SetupUart2,
+    requires = [SatpSetup, IrqSetup]
+);
 impl TestRunner for SetupUart2Tests {
     fn run(&mut self) { setup_uart2(); }
 }
@@ -27,78 +37,28 @@ impl TestRunner for SetupUart2Tests {
 pub unsafe fn init_clock_asic(freq_hz: u32) -> u32 {
     use utra::sysctrl;
     let daric_cgu = sysctrl::HW_SYSCTRL_BASE as *mut u32;
-    /*
-       Code notes from RTL:
-       assign pll_m = ipc_pllmn[16:12];
-       assign pll_n = ipc_pllmn[11: 0];
-       assign pll_f = ipc_pllf[23: 0];
-       assign pll_fen = ipc_pllf[24];
-       assign pll_q00 = ipc_pllq[ 2: 0];
-       assign pll_q10 = ipc_pllq[ 6: 4];
-       assign pll_q01 = ipc_pllq[10: 8];
-       assign pll_q11 = ipc_pllq[14:12];
-
-       Clko0 = Fvco / (pllq[ 2:0] + 1) / (pllq[ 6:4] + 1)
-       Clko1 = Fvco / (pllq[10:8] + 1) / (pllq[14:12] + 1)
-       Fvco target is 2GHz (1-3GHz range)
-
-      .gvco_bias ( pll_bias[7:6] ),
-      .cpp_bias  ( pll_bias[5:3] ),
-      .cpi_bias  ( pll_bias[2:0] ),
-    */
-    // Derive VCO frequency from legal, even dividers that get us close to our target frequency
-    const TARGET_VCO_HZ: u32 = 1_600_000_000; // 1.6GHz
-    let final_div: u32 = TARGET_VCO_HZ / freq_hz;
-    // fclk_div has to be a power of 2
-    let fclk_div =
-        if (1 << final_div.ilog2()) != final_div { 1 << (final_div.ilog2() + 1) } else { final_div };
-    let vco_actual: u32 = fclk_div * freq_hz;
-    if vco_actual < 1_000_000_000 || vco_actual > 3_000_000_000 {
-        crate::println!("Warning: VCO out of range: {}", vco_actual);
+    let pll = tests::pll::PllConfig::new(freq_hz);
+    if !pll.vco_in_range() {
+        crate::println!("Warning: VCO out of range: {}", pll.vco_hz);
     }
-    const TARGET_PERCLK_HZ: u32 = 100_000_000; // 100 MHz
-    let perclk_np_div: u32 = vco_actual / TARGET_PERCLK_HZ;
-    let perclk_div = if (1 << perclk_np_div.ilog2()) != perclk_np_div {
-        1 << (perclk_np_div.ilog2() + 1)
-    } else {
-        perclk_np_div
-    };
-    let ilog2_fdiv = fclk_div.ilog2();
-    let ilog2_pdiv = perclk_div.ilog2();
-    let pll_q0_0 = (1 << (ilog2_fdiv / 2)) - 1;
-    let pll_q1_0 = (1 << (ilog2_fdiv / 2 + ilog2_fdiv % 2)) - 1;
-    let pll_q0_1 = (1 << (ilog2_pdiv / 2)) - 1;
-    let pll_q1_1 = (1 << (ilog2_pdiv / 2 + ilog2_pdiv % 2)) - 1;
-    if pll_q0_0 > 7 || pll_q0_1 > 7 || pll_q1_0 > 7 || pll_q1_1 > 7 {
+    if !pll.q_in_range() {
         crate::println!(
             "Warning: PLLQ out of range: 0_0:{} 1_0:{} 0_1:{} 1_1:{}",
-            pll_q0_0,
-            pll_q1_0,
-            pll_q0_1,
-            pll_q1_1
+            pll.q[0],
+            pll.q[1],
+            pll.q[2],
+            pll.q[3]
         );
     }
-    // this is the pllq value
-    let pllq = (pll_q0_0 & 7) | ((pll_q1_0 & 7) << 4) | ((pll_q0_1 & 7) << 8) | ((pll_q1_1 & 7) << 12);
-
-    // now, program the VCO to get to as close to vco_actual
-    const FREF_HZ: u32 = 48_000_000;
-    // adjust m so that PFD runs between 4-16MHz (target 8MHz)
-    const PREDIV_M: u32 = 6;
-    let fref_hz = FREF_HZ / PREDIV_M;
-    assert!(fref_hz == 8_000_000);
-
-    let ni = vco_actual / fref_hz;
-    if ni >= 4096 || ni < 8 {
-        crate::println!("Warning: ni out of range: {}", ni);
+    if !pll.ni_in_range() {
+        crate::println!("Warning: ni out of range: {}", pll.ni);
     }
-    let pllmn = (PREDIV_M << 12) | ni & 0xFFF; // m is set to PREDIV_M, lower 12 bits is nf
-    let frac_n = ((vco_actual as f32 / fref_hz as f32) - ni as f32).max(0 as f32);
-    let pllf: u32 = (frac_n * ((1 << 24) as f32)) as u32;
-    if pllf >= 1 << 24 {
-        crate::println!("Warning nf out of range: 0x{:x}", pllf);
+    if !pll.pllf_in_range() {
+        crate::println!("Warning nf out of range: 0x{:x}", pll.pllf);
     }
-    let n_frac = if pllf != 0 { pllf | 1 << 24 } else { 0 }; // set the frac enable bit if needed
+    let pllq = pll.pllq();
+    let pllmn = pll.pllmn();
+    let n_frac = pll.n_frac();
 
     crate::println!("pllq: 0x{:x}, pllmn: 0x{:x}, n_frac: 0x{:x}", pllq, pllmn, n_frac);
 
@@ -211,7 +171,7 @@ pub unsafe fn init_clock_asic(freq_hz: u32) -> u32 {
     }
     crate::println!("PLL configured to {} MHz", freq_hz / 1_000_000);
 
-    vco_actual / perclk_div
+    pll.perclk_hz()
 }
 
 // This function supercedes init_clock_asic() and needs to be back-ported
@@ -475,19 +435,11 @@ pub unsafe fn early_init() {
         daric_cgu.add(utra::sysctrl::SFR_CGUSET.offset()).write_volatile(0x32);
     }
     // enable DUART
-    let duart = utra::duart::HW_DUART_BASE as *mut u32;
-    duart.add(utra::duart::SFR_CR.offset()).write_volatile(0);
-    duart.add(utra::duart::SFR_ETUC.offset()).write_volatile(24);
-    duart.add(utra::duart::SFR_CR.offset()).write_volatile(1);
+    debug::Uart {}.init(debug::DEFAULT_ETUC);
 }
 
 // these register do not exist in our local simulation model
 pub fn setup_uart2() {
-    const UART_IFRAM_ADDR: usize = utralib::HW_IFRAM0_MEM + utralib::HW_IFRAM0_MEM_LEN - 4096;
-    use cramium_api::iox::{IoxDir, IoxEnable, IoxFunction, IoxPort};
-    use cramium_api::udma::*;
-    use cramium_hal::iox::Iox;
-    use cramium_hal::udma;
     use cramium_hal::udma::Udma;
 
     let mut uart = debug::Uart {};
@@ -506,6 +458,28 @@ pub fn setup_uart2() {
     uart.tiny_write_str("\r");
 
     uart.tiny_write_str("udma\r");
+    let mut udma_uart = uart2_handle();
+
+    uart.print_hex_word(udma_uart.csr().r(utra::udma_uart_1::REG_UART_SETUP));
+    let mut tx_buf = [0u8; 32];
+    for (i, t) in tx_buf.iter_mut().enumerate() {
+        *t = '0' as char as u8 + i as u8;
+    }
+    for _ in 0..16 {
+        udma_uart.write(&tx_buf);
+    }
+    uart.tiny_write_str("udma done\r");
+}
+
+/// Sets up the pins, clock, events and baud rate of the UDMA UART (UART1 on PD13/PD14), and
+/// returns a handle to it.
+fn uart2_handle() -> cramium_hal::udma::Uart {
+    const UART_IFRAM_ADDR: usize = utralib::HW_IFRAM0_MEM + utralib::HW_IFRAM0_MEM_LEN - 4096;
+    use cramium_api::iox::{IoxDir, IoxEnable, IoxFunction, IoxPort};
+    use cramium_api::udma::*;
+    use cramium_hal::iox::Iox;
+    use cramium_hal::udma;
+
     //  UART_RX_A[1] = PD13
     //  UART_RX_A[1] = PD14
     let iox = Iox::new(utra::iox::HW_IOX_BASE as *mut u32);
@@ -532,7 +506,7 @@ pub fn setup_uart2() {
     );
 
     let baudrate: u32 = 115200;
-    let freq: u32 = 45_882_000;
+    let freq: u32 = DEFAULT_PERCLK_HZ;
 
     // the address of the UART buffer is "hard-allocated" at an offset one page from the top of
     // IFRAM0. This is a convention that must be respected by the UDMA UART library implementation
@@ -543,17 +517,20 @@ pub fn setup_uart2() {
         udma::Uart::get_handle(utra::udma_uart_1::HW_UDMA_UART_1_BASE, uart_buf_addr, uart_buf_addr)
     };
     let div: u32 = (freq + baudrate / 2) / baudrate;
-    uart.tiny_write_str("divder: 0x");
-    report_api(div);
+    Subsystem::Init.report(Event::Value(event::init::UART_DIV), &[div]);
     udma_uart.set_baud(baudrate, freq);
+    udma_uart
+}
 
-    uart.print_hex_word(udma_uart.csr().r(utra::udma_uart_1::REG_UART_SETUP));
-    let mut tx_buf = [0u8; 32];
-    for (i, t) in tx_buf.iter_mut().enumerate() {
-        *t = '0' as char as u8 + i as u8;
-    }
-    for _ in 0..16 {
-        udma_uart.write(&tx_buf);
-    }
-    uart.tiny_write_str("udma done\r");
+/// Sets up the UDMA UART to receive in polling mode, so that `debug::Uart::getc()` can read it.
+/// Output still goes to the DUART.
+pub fn setup_console_rx() {
+    use cramium_hal::udma::Udma;
+
+    let mut udma_uart = uart2_handle();
+    let csr = udma_uart.csr_mut();
+    csr.rmwf(utra::udma_uart_1::REG_UART_SETUP_R_UART_RX_CLEAN_FIFO, 1);
+    csr.rmwf(utra::udma_uart_1::REG_UART_SETUP_R_UART_RX_POLLING_EN, 1);
+    csr.rmwf(utra::udma_uart_1::REG_UART_SETUP_R_UART_EN_RX, 1);
+    csr.rmwf(utra::udma_uart_1::REG_UART_SETUP_R_UART_RX_CLEAN_FIFO, 0);
 }
@@ -12,32 +12,41 @@
 use riscv::register::{scause, sie, vexriscv::sim, vexriscv::sip};
 use utralib::generated::*;
 
+use crate::event::{self, Event, Subsystem};
 use crate::*;
 
 const IRQ_TESTS: usize = 1;
-crate::impl_test!(IrqTests, "IRQ", IRQ_TESTS);
+crate::impl_test!(IrqTests, "IRQ", IRQ_TESTS, Irq, requires = [IrqSetup]);
 impl TestRunner for IrqTests {
     fn run(&mut self) {
         irq_test();
-        self.passing_tests += 1;
+        self.cases.pass("irq_test");
     }
 }
 
 const WFI_TESTS: usize = 1;
-crate::impl_test!(WfiTests, "WFI", WFI_TESTS);
+crate::impl_test!(WfiTests, "WFI", WFI_TESTS, Wfi, requires = [IrqSetup]);
 impl TestRunner for WfiTests {
     fn run(&mut self) {
         wfi_test();
-        self.passing_tests += 1;
+        // wfi_test() speeds up the tick rate; put back the rate the harness budgets assume
+        reset_ticktimer();
+        self.cases.pass("wfi_test");
     }
+
+    /// wfi_test() owns the ticktimer alarm, so it cannot run under the watchdog
+    fn budget_ticks(&self) -> u32 { 0 }
+
+    fn buffered_log(&self) -> bool { true }
 }
 
 const IRQ_SETUP_TESTS: usize = 1;
-crate::impl_test!(IrqSetup, "IRQ Setup", IRQ_SETUP_TESTS);
+// the supervisor-mode interrupt CSRs are only usable once satp_setup() has dropped out of machine mode
+crate::impl_test!(IrqSetup, "IRQ Setup", IRQ_SETUP_TESTS, IrqSetup, requires = [SatpSetup]);
 impl TestRunner for IrqSetup {
     fn run(&mut self) {
         irq_setup();
-        self.passing_tests += 1;
+        self.cases.pass("irq_setup");
     }
 }
 
@@ -87,7 +96,7 @@ pub fn irq_setup() {
         );
     }
 
-    report_api(0x1dcd_0000);
+    Subsystem::IrqSetup.report(Event::Begin, &[]);
 
     let mut irqarray18 = CSR::new(utra::irqarray18::HW_IRQARRAY18_BASE as *mut u32);
     let mut irqarray19 = CSR::new(utra::irqarray19::HW_IRQARRAY19_BASE as *mut u32);
@@ -111,26 +120,26 @@ pub fn irq_setup() {
     // must enable external interrupts on the CPU for any of the above to matter
     unsafe { sie::set_sext() };
 
-    report_api(0x1dcd_600d);
+    Subsystem::IrqSetup.report(Event::End, &[]);
 }
 
 pub fn irq_test() {
     // trigger an interrupt
-    report_api(0x3dcd_0000);
+    Subsystem::Irq.report(Event::Begin, &[]);
 
     let mut main = CSR::new(utra::main::HW_MAIN_BASE as *mut u32);
     // simulate hw trigger from IRQ0
-    report_api(0x3dcd_0001);
+    Subsystem::Irq.report(Event::Step(1), &[]);
     main.wfo(utra::main::IRQTEST0_TRIGGER, 4);
     // software-only trigger from IRQ2
-    report_api(0x3dcd_0003);
+    Subsystem::Irq.report(Event::Step(3), &[]);
     let mut irqarray19 = CSR::new(utra::irqarray19::HW_IRQARRAY19_BASE as *mut u32);
     irqarray19.wfo(utra::irqarray19::EV_SOFT_TRIGGER, 0x80);
-    report_api(0x3dcd_600d);
+    Subsystem::Irq.report(Event::End, &[]);
 }
 
 pub fn wfi_test() {
-    report_api(0x03f1_0000);
+    Subsystem::Wfi.report(Event::Begin, &[]);
     let mut tt = CSR::new(utra::ticktimer::HW_TICKTIMER_BASE as *mut u32);
     tt.wo(utra::ticktimer::CLOCKS_PER_TICK, 10000); // short-ish time to wake up
     tt.wfo(utra::ticktimer::CONTROL_RESET, 1);
@@ -152,7 +161,7 @@ pub fn wfi_test() {
         );
     }
     tt.wo(utra::ticktimer::MSLEEP_TARGET0, 0xffff_ffff); // sometime way out there so we don't see it again during this test.
-    report_api(0x03f1_600d);
+    Subsystem::Wfi.report(Event::End, &[]);
 }
 
 // Notes: 403 CPU cycles to enter the handler (~4us wall-clock @ 100MHz).
@@ -231,6 +240,23 @@ pub unsafe extern "C" fn _start_trap() -> ! {
     }
 }
 
+/// Re-enables interrupts on the way out of a trap. Returns the resulting `sstatus`.
+pub fn reenable_irqs() -> u32 {
+    let status: u32;
+    unsafe {
+        #[rustfmt::skip]
+        core::arch::asm!(
+            "csrr        t0, sstatus",
+            "ori         t0, t0, 3",
+            "csrw        sstatus, t0",
+            "csrr        {status}, sstatus",
+            status = out(reg) status,
+        );
+    }
+    unsafe { sie::set_sext() };
+    status
+}
+
 #[export_name = "_resume_context"]
 #[inline(never)]
 pub unsafe extern "C" fn _resume_context(registers: u32) -> ! {
@@ -291,12 +317,17 @@ pub extern "C" fn trap_handler(
     _a7: usize,
 ) -> ! {
     let mut main = CSR::new(utra::main::HW_MAIN_BASE as *mut u32);
-    #[cfg(feature = "debug-irq")]
-    report_api(0x2dcd_0000);
+    // snapshot this before any reporting in the handler overwrites it
+    let last_report = crate::harness::last_report();
+    let trace = crate::log_enabled!(Trace);
+    if trace {
+        Subsystem::Trap.report(Event::Begin, &[]);
+    }
 
     let sc: scause::Scause = scause::read();
-    #[cfg(feature = "debug-irq")]
-    report_api(sc.bits() as u32);
+    if trace {
+        Subsystem::Trap.report(Event::Value(event::trap::SCAUSE), &[sc.bits() as u32]);
+    }
     // 2 is illegal instruction
     if sc.bits() == 2 {
         // skip past the illegal instruction, since we are just testing that they trigger exceptions.
@@ -311,14 +342,15 @@ pub extern "C" fn trap_handler(
     } else if sc.bits() == 0x8000_0009 {
         // external interrupt. find out which ones triggered it, and clear the source.
         let irqs_pending = sip::read();
-        #[cfg(feature = "debug-irq")]
-        report_api(irqs_pending as u32);
+        if trace {
+            Subsystem::Trap.report(Event::Value(event::trap::PENDING), &[irqs_pending as u32]);
+        }
         if (irqs_pending & (1 << 18)) != 0 {
             let mut irqarray18 = CSR::new(utra::irqarray18::HW_IRQARRAY18_BASE as *mut u32);
             #[cfg(feature = "quanta-test")]
             {
                 if irqarray18.rf(utra::irqarray18::EV_PENDING_PIOIRQ0_DUPE) != 0 {
-                    report_api(0x51C2_1111);
+                    Subsystem::Trap.report(Event::Step(event::trap::PIO_IRQ), &[]);
                     let mut pio_ss = xous_pio::PioSharedState::new();
                     pio_ss.pio.wo(utra::rp_pio::SFR_IRQ, 1 << 0); // clear irq bit 0
                 }
@@ -326,22 +358,25 @@ pub extern "C" fn trap_handler(
             // handle irq18 hw test
             main.wfo(utra::main::IRQTEST0_TRIGGER, 0);
             let pending = irqarray18.r(utra::irqarray18::EV_PENDING);
-            report_api(pending << 16 | 18); // encode the irq bank number and bit number as [bit | bank]
+            Subsystem::Trap.report(Event::Value(event::trap::IRQ_BANK + 18), &[pending]);
             irqarray18.wo(utra::irqarray18::EV_PENDING, pending);
         }
         if (irqs_pending & (1 << 19)) != 0 {
             // handle irq19 sw trigger test
             let mut irqarray19 = CSR::new(utra::irqarray19::HW_IRQARRAY19_BASE as *mut u32);
             let pending = irqarray19.r(utra::irqarray19::EV_PENDING);
-            report_api(pending << 16 | 19); // encode the irq bank number and bit number as [bit | bank]
+            Subsystem::Trap.report(Event::Value(event::trap::IRQ_BANK + 19), &[pending]);
             irqarray19.wo(utra::irqarray19::EV_PENDING, pending);
             // software interrupt should not require a 0-write to reset it
         }
         if (irqs_pending & (1 << utra::ticktimer::TICKTIMER_IRQ)) != 0 {
             let mut tt = CSR::new(utra::ticktimer::HW_TICKTIMER_BASE as *mut u32);
-            report_api(utra::ticktimer::TICKTIMER_IRQ as u32); // encode the irq bank number and bit number as [bit | bank]
+            Subsystem::Trap
+                .report(Event::Value(event::trap::IRQ_BANK + utra::ticktimer::TICKTIMER_IRQ as u16), &[]);
             tt.wfo(utra::ticktimer::EV_PENDING_ALARM, 1); // clear the interrupt
             tt.wfo(utra::ticktimer::EV_ENABLE_ALARM, 0); // mask out the wakeup alarm
+            // the same alarm backs the harness watchdog
+            crate::harness::watchdog_check(crate::satp::SCRATCH_PAGE as *mut u32, last_report);
         }
         if (irqs_pending & (1 << utra::timer0::TIMER0_IRQ)) != 0 {
             let mut timer0 = CSR::new(utra::timer0::HW_TIMER0_BASE as *mut u32);
@@ -351,31 +386,26 @@ pub extern "C" fn trap_handler(
     }
 
     // report interrupt status
-    #[cfg(feature = "debug-irq")]
-    {
-        report_api(riscv::register::sepc::read() as u32);
-        report_api(riscv::register::stval::read() as u32);
-        report_api(sim::read() as u32);
-    }
-
-    // re-enable interrupts
-    let status: u32;
-    unsafe {
-        #[rustfmt::skip]
-        core::arch::asm!(
-            "csrr        t0, sstatus",
-            "ori         t0, t0, 3",
-            "csrw        sstatus, t0",
-            "csrr        {status}, sstatus",
-            status = out(reg) status,
+    if trace {
+        Subsystem::Trap.report(
+            Event::Value(event::trap::CSRS),
+            &[
+                riscv::register::sepc::read() as u32,
+                riscv::register::stval::read() as u32,
+                sim::read() as u32,
+            ],
         );
     }
-    unsafe { sie::set_sext() };
+
+    crate::logbuf::pump();
+
+    let status = reenable_irqs();
     // for some reason, this report print is important for proper function.
-    report_api(status);
+    Subsystem::Trap.report(Event::Value(event::trap::STATUS), &[status]);
 
     // drop us back to user mode
-    #[cfg(feature = "debug-irq")]
-    report_api(0x2dcd_600d);
+    if trace {
+        Subsystem::Trap.report(Event::End, &[]);
+    }
     unsafe { _resume_context(crate::satp::SCRATCH_PAGE as u32) };
 }
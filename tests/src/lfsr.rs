@@ -0,0 +1,121 @@
+// (c) Copyright CrossBar, Inc. 2024.
+//
+// This documentation describes Open Hardware and is licensed under the
+// [CERN-OHL-W-2.0].
+//
+// You may redistribute and modify this documentation under the terms of the
+// [CERN-OHL- W-2.0 (http://ohwr.org/cernohl)]. This documentation is
+// distributed WITHOUT ANY EXPRESS OR IMPLIED WARRANTY, INCLUDING OF
+// MERCHANTABILITY, SATISFACTORY QUALITY AND FITNESS FOR A PARTICULAR PURPOSE.
+// Please see the [CERN-OHL- W-2.0] for applicable conditions.
+
+//! LFSRs used to generate test vectors and access patterns.
+
+/// used to generate some test vectors
+pub fn lfsr_next_u32(state: u32) -> u32 {
+    let bit = ((state >> 31) ^ (state >> 21) ^ (state >> 1) ^ state) & 1;
+
+    (state << 1) + bit
+}
+
+/* some LFSR terms
+    3 3,2
+    4 4,3
+    5 5,3
+    6 6,5
+    7 7,6
+    8 8,6,5,4
+    9 9,5  <--
+    10 10,7
+    11 11,9
+    12 12,6,4,1
+    13 13,4,3,1
+    14 14,5,3,1
+    15 15,14
+    16 16,15,13,4
+    17 17,14
+    18 18,11
+    19 19,6,2,1
+    20 20,17
+
+    32 32,22,2,1:
+    let bit = ((state >> 31) ^
+               (state >> 21) ^
+               (state >>  1) ^
+               (state >>  0)) & 1;
+
+*/
+/// our desired test length is 512 entries, so pick an LFSR with a period of 2^9-1...
+pub fn lfsr_next(state: u16) -> u16 {
+    let bit = ((state >> 8) ^ (state >> 4)) & 1;
+
+    ((state << 1) + bit) & 0x1_FF
+}
+
+/// shortened test length is 16 entries, so pick an LFSR with a period of 2^4-1...
+pub fn lfsr_next_16(state: u16) -> u16 {
+    let bit = ((state >> 3) ^ (state >> 2)) & 1;
+
+    ((state << 1) + bit) & 0xF
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Steps `next` from `seed` until it comes back around, returning the period.
+    fn period(seed: u16, next: fn(u16) -> u16) -> usize {
+        let mut state = next(seed);
+        let mut steps = 1;
+        while state != seed {
+            assert_ne!(state, 0, "LFSR locked up");
+            state = next(state);
+            steps += 1;
+        }
+        steps
+    }
+
+    #[test]
+    fn lfsr_next_is_maximal() {
+        assert_eq!(period(1, lfsr_next), (1 << 9) - 1);
+    }
+
+    #[test]
+    fn lfsr_next_visits_every_nonzero_state() {
+        let mut seen = [false; 512];
+        let mut state = 1;
+        for _ in 0..511 {
+            assert!(!seen[state as usize], "state {:#x} repeated", state);
+            seen[state as usize] = true;
+            state = lfsr_next(state);
+        }
+        assert!(!seen[0]);
+        assert!(seen[1..].iter().all(|&s| s));
+    }
+
+    #[test]
+    fn lfsr_next_16_is_maximal() {
+        assert_eq!(period(1, lfsr_next_16), (1 << 4) - 1);
+    }
+
+    #[test]
+    fn lfsr_next_known_sequence() {
+        // taps at bits 8 and 4 only start feeding back once the seed reaches them
+        let mut state = 1;
+        let mut seq = [0u16; 10];
+        for s in seq.iter_mut() {
+            state = lfsr_next(state);
+            *s = state;
+        }
+        assert_eq!(seq, [0x2, 0x4, 0x8, 0x10, 0x21, 0x42, 0x84, 0x108, 0x011, 0x023]);
+    }
+
+    #[test]
+    fn lfsr_next_u32_stays_nonzero() {
+        let mut state = 1;
+        for _ in 0..100_000 {
+            state = lfsr_next_u32(state);
+            assert_ne!(state, 0);
+        }
+    }
+}
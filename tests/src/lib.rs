@@ -0,0 +1,29 @@
+// (c) Copyright CrossBar, Inc. 2024.
+//
+// This documentation describes Open Hardware and is licensed under the
+// [CERN-OHL-W-2.0].
+//
+// You may redistribute and modify this documentation under the terms of the
+// [CERN-OHL- W-2.0 (http://ohwr.org/cernohl)]. This documentation is
+// distributed WITHOUT ANY EXPRESS OR IMPLIED WARRANTY, INCLUDING OF
+// MERCHANTABILITY, SATISFACTORY QUALITY AND FITNESS FOR A PARTICULAR PURPOSE.
+// Please see the [CERN-OHL- W-2.0] for applicable conditions.
+
+//! The parts of the tests that don't touch hardware: register encodings, divider math and the
+//! like. The firmware in `main.rs` uses them through `tests::`, and with the `std` feature they
+//! also build for the host, so that they can be unit-tested without a simulator:
+//!
+//! ```text
+//! cargo test --lib --features std
+//! ```
+//!
+//! Nothing in here may use MMIO or `crate::println!`; anything the firmware needs to report is
+//! returned to the caller instead.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub mod acram;
+pub mod lfsr;
+pub mod mbox_frame;
+pub mod pll;
+pub mod sv32;
@@ -0,0 +1,272 @@
+// (c) Copyright CrossBar, Inc. 2024.
+//
+// This documentation describes Open Hardware and is licensed under the
+// [CERN-OHL-W-2.0].
+//
+// You may redistribute and modify this documentation under the terms of the
+// [CERN-OHL- W-2.0 (http://ohwr.org/cernohl)]. This documentation is
+// distributed WITHOUT ANY EXPRESS OR IMPLIED WARRANTY, INCLUDING OF
+// MERCHANTABILITY, SATISFACTORY QUALITY AND FITNESS FOR A PARTICULAR PURPOSE.
+// Please see the [CERN-OHL- W-2.0] for applicable conditions.
+
+//! Leveled logging on top of `println!`, with a threshold per module.
+//!
+//! `error!`, `warn!`, `info!`, `debug!` and `trace!` print a line if their level is at or below
+//! the threshold of the module they are used in. `log_enabled!` guards diagnostics that aren't
+//! prints, such as `report_api` traces. Plain `println!` always prints.
+//!
+//! Thresholds are set at build time by the `TESTS_LOG` environment variable, a comma-separated
+//! list of `level` (the default for all modules) and `module=level` entries, e.g.
+//! `TESTS_LOG=warn,mbox=trace`. The default is `info`; the `debug-irq` feature is shorthand for
+//! `irqs=trace`. At runtime, a testbench, debugger or the console can override them through the
+//! table at `LOG_TABLE`.
+
+use crate::harness::HARNESS_BASE;
+
+/// Runtime threshold overrides; see `LogTable`.
+pub const LOG_TABLE: usize = HARNESS_BASE + 0x20;
+/// Marks the runtime table as valid
+pub const LOG_MAGIC: u32 = 0x1e7e_1500;
+/// `LogTable` entry that keeps the build-time threshold
+pub const KEEP: u8 = 0xff;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum Level {
+    Off = 0,
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+    Trace = 5,
+}
+
+impl Level {
+    pub const ALL: [Level; 6] =
+        [Level::Off, Level::Error, Level::Warn, Level::Info, Level::Debug, Level::Trace];
+
+    pub fn from_u8(level: u8) -> Option<Level> { Level::ALL.get(level as usize).copied() }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Level::Off => "off",
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Level> { Level::ALL.iter().copied().find(|l| l.name() == name) }
+}
+
+/// Modules with their own threshold, by their index in `LogTable::levels`. Modules not listed
+/// share the `other` entry. Append only: the indices are part of the runtime table layout.
+pub const MODULES: [&str; 16] = [
+    "harness", "irqs", "satp", "mbox", "rram", "udma", "ramtests", "bio", "aes", "timer0", "gpio", "cam",
+    "init", "pl230", "sce", "other",
+];
+const OTHER: usize = MODULES.len() - 1;
+
+/// Layout of the runtime table at `LOG_TABLE`.
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct LogTable {
+    /// Must be `LOG_MAGIC` for the table to be honored
+    pub magic: u32,
+    /// Threshold for every module whose `levels` entry is `KEEP`; `KEEP` to leave them alone
+    pub all: u32,
+    /// Threshold for each of `MODULES`, or `KEEP`
+    pub levels: [u8; MODULES.len()],
+}
+
+fn table() -> *mut LogTable { LOG_TABLE as *mut LogTable }
+
+/// Returns the runtime table, if one has been loaded.
+pub fn log_table() -> Option<LogTable> {
+    let table = unsafe { table().read_volatile() };
+    if table.magic == LOG_MAGIC { Some(table) } else { None }
+}
+
+/// Sets the runtime threshold of `module` (an index into `MODULES`), or of every module if
+/// `module` is `None`. `None` for `level` goes back to the build-time threshold.
+pub fn set_level(module: Option<usize>, level: Option<Level>) {
+    let mut t =
+        log_table().unwrap_or(LogTable { magic: LOG_MAGIC, all: KEEP as u32, levels: [KEEP; MODULES.len()] });
+    let level = level.map(|l| l as u8).unwrap_or(KEEP);
+    match module {
+        Some(m) => t.levels[m] = level,
+        None => {
+            t.all = level as u32;
+            t.levels = [KEEP; MODULES.len()];
+        }
+    }
+    unsafe { table().write_volatile(t) };
+}
+
+/// Current threshold of `module`, an index into `MODULES`.
+pub fn threshold(module: usize) -> Level {
+    if let Some(t) = log_table() {
+        if let Some(level) = Level::from_u8(t.levels[module]) {
+            return level;
+        }
+        if let Some(level) = Level::ALL.get(t.all as usize).copied() {
+            return level;
+        }
+    }
+    BUILD_LEVELS[module]
+}
+
+pub fn enabled(level: Level, module: usize) -> bool { level <= threshold(module) }
+
+/// Maps a `module_path!()` to its index in `MODULES`, by its first component below the crate.
+pub const fn module_id(path: &str) -> usize {
+    let path = path.as_bytes();
+    // skip the crate name
+    let mut start = 0;
+    while start + 1 < path.len() && !(path[start] == b':' && path[start + 1] == b':') {
+        start += 1;
+    }
+    start += 2;
+    let mut end = start;
+    while end < path.len() && path[end] != b':' {
+        end += 1;
+    }
+    let mut m = 0;
+    while m < OTHER {
+        if bytes_eq(path, start, end, MODULES[m]) {
+            return m;
+        }
+        m += 1;
+    }
+    OTHER
+}
+
+const fn bytes_eq(s: &[u8], start: usize, end: usize, other: &str) -> bool {
+    let other = other.as_bytes();
+    if start > end || end > s.len() || end - start != other.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < other.len() {
+        if s[start + i] != other[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+const fn parse_level(s: &[u8], start: usize, end: usize) -> Level {
+    let mut l = 0;
+    while l < Level::ALL.len() {
+        let level = Level::ALL[l];
+        if bytes_eq(s, start, end, level.name()) {
+            return level;
+        }
+        l += 1;
+    }
+    panic!("TESTS_LOG: unknown log level");
+}
+
+const BUILD_SPEC: &str = match option_env!("TESTS_LOG") {
+    Some(spec) => spec,
+    None => "",
+};
+
+/// Parses `spec` (the `TESTS_LOG` syntax) into a threshold per module. Evaluated at compile time,
+/// so a malformed `TESTS_LOG` fails the build.
+const fn build_levels(spec: &str) -> [Level; MODULES.len()] {
+    let mut levels = [Level::Info; MODULES.len()];
+    if cfg!(feature = "debug-irq") {
+        levels[module_id("tests::irqs")] = Level::Trace;
+    }
+    let s = spec.as_bytes();
+    let mut start = 0;
+    while start < s.len() {
+        let mut end = start;
+        let mut eq = None;
+        while end < s.len() && s[end] != b',' {
+            if s[end] == b'=' {
+                eq = Some(end);
+            }
+            end += 1;
+        }
+        match eq {
+            Some(eq) => {
+                let level = parse_level(s, eq + 1, end);
+                let mut m = 0;
+                let mut found = false;
+                while m < MODULES.len() {
+                    if bytes_eq(s, start, eq, MODULES[m]) {
+                        levels[m] = level;
+                        found = true;
+                    }
+                    m += 1;
+                }
+                if !found {
+                    panic!("TESTS_LOG: unknown module");
+                }
+            }
+            None if end > start => {
+                let level = parse_level(s, start, end);
+                let mut m = 0;
+                while m < MODULES.len() {
+                    levels[m] = level;
+                    m += 1;
+                }
+            }
+            None => (),
+        }
+        start = end + 1;
+    }
+    levels
+}
+
+/// Thresholds set at build time
+pub const BUILD_LEVELS: [Level; MODULES.len()] = build_levels(BUILD_SPEC);
+
+/// True if messages at `$level` (a `Level` variant name) are enabled for the calling module.
+#[macro_export]
+macro_rules! log_enabled {
+    ($level:ident) => {{
+        const MODULE: usize = $crate::log::module_id(module_path!());
+        $crate::log::enabled($crate::log::Level::$level, MODULE)
+    }};
+}
+
+/// Prints a line, like `println!`, if `$level` is enabled for the calling module.
+#[macro_export]
+macro_rules! log_at {
+    ($level:ident, $($args:tt)+) => {{
+        if $crate::log_enabled!($level) {
+            $crate::println!($($args)+);
+        }
+    }};
+}
+
+#[macro_export]
+macro_rules! error {
+    ($($args:tt)+) => { $crate::log_at!(Error, $($args)+) };
+}
+
+#[macro_export]
+macro_rules! warn {
+    ($($args:tt)+) => { $crate::log_at!(Warn, $($args)+) };
+}
+
+#[macro_export]
+macro_rules! info {
+    ($($args:tt)+) => { $crate::log_at!(Info, $($args)+) };
+}
+
+#[macro_export]
+macro_rules! debug {
+    ($($args:tt)+) => { $crate::log_at!(Debug, $($args)+) };
+}
+
+#[macro_export]
+macro_rules! trace {
+    ($($args:tt)+) => { $crate::log_at!(Trace, $($args)+) };
+}
@@ -0,0 +1,145 @@
+// (c) Copyright CrossBar, Inc. 2024.
+//
+// This documentation describes Open Hardware and is licensed under the
+// [CERN-OHL-W-2.0].
+//
+// You may redistribute and modify this documentation under the terms of the
+// [CERN-OHL- W-2.0 (http://ohwr.org/cernohl)]. This documentation is
+// distributed WITHOUT ANY EXPRESS OR IMPLIED WARRANTY, INCLUDING OF
+// MERCHANTABILITY, SATISFACTORY QUALITY AND FITNESS FOR A PARTICULAR PURPOSE.
+// Please see the [CERN-OHL- W-2.0] for applicable conditions.
+
+//! Ring-buffered output for `print!`/`println!`.
+//!
+//! `Uart::putc` spin-waits on the DUART for every byte, which perturbs timing-sensitive suites. While
+//! buffering is on, output goes into a ring in SRAM instead. The ring is drained to the DUART a
+//! byte at a time from the trap handler, whenever the DUART is idle, and completely by `flush()`,
+//! which the harness calls when a buffered suite ends and the panic handler calls before
+//! reporting. Output is never dropped: if the ring fills up, the writer stalls and drains it.
+//!
+//! Buffering is on while a suite whose `TestRunner::buffered_log()` returns true is running, or
+//! for every suite if `FLAG_BUFFERED_LOG` is set in the selection table.
+
+use core::fmt::{Error, Write};
+
+use crate::debug;
+
+/// Ring state; see `link.x`
+pub const LOG_STATE: usize = 0x6110_2000;
+/// Ring data
+pub const LOG_DATA: usize = 0x6110_3000;
+/// Ring size in bytes; must be a power of two
+pub const LOG_LEN: usize = 0x1000;
+
+/// Marks buffering as on. SRAM is not initialized at boot, so anything else means "off".
+const BUFFERED_MAGIC: u32 = 0xb0ff_e2ed;
+
+#[repr(C)]
+struct LogState {
+    /// `BUFFERED_MAGIC` while output is being buffered
+    buffered: u32,
+    /// Nonzero while a foreground `flush()` is draining the ring, so the trap handler keeps out
+    draining: u32,
+    /// Free-running write index; only the writer advances it
+    head: u32,
+    /// Free-running read index; only the drain advances it
+    tail: u32,
+}
+
+fn state() -> *mut LogState { LOG_STATE as *mut LogState }
+
+fn data() -> *mut u8 { LOG_DATA as *mut u8 }
+
+/// Empties the ring and turns buffering off. Called from `harness::init()`.
+pub fn init() {
+    unsafe {
+        (*state()).buffered = 0;
+        (*state()).draining = 0;
+        (*state()).head = 0;
+        (*state()).tail = 0;
+    }
+}
+
+pub fn is_buffered() -> bool {
+    unsafe { core::ptr::addr_of!((*state()).buffered).read_volatile() == BUFFERED_MAGIC }
+}
+
+/// Turns buffering on or off. Turning it off drains whatever is left in the ring first.
+pub fn set_buffered(on: bool) {
+    if !on {
+        flush();
+    }
+    unsafe {
+        core::ptr::addr_of_mut!((*state()).buffered).write_volatile(if on { BUFFERED_MAGIC } else { 0 })
+    }
+}
+
+fn head() -> u32 { unsafe { core::ptr::addr_of!((*state()).head).read_volatile() } }
+
+fn tail() -> u32 { unsafe { core::ptr::addr_of!((*state()).tail).read_volatile() } }
+
+fn push(c: u8) {
+    if head().wrapping_sub(tail()) as usize >= LOG_LEN {
+        flush();
+    }
+    let head = head();
+    unsafe {
+        data().add(head as usize & (LOG_LEN - 1)).write_volatile(c);
+        core::ptr::addr_of_mut!((*state()).head).write_volatile(head.wrapping_add(1));
+    }
+}
+
+/// Drains the ring to the DUART, waiting for it as needed. The ring is always empty while
+/// buffering is off, so this does nothing then; that also keeps a panic before `init()` from
+/// draining uninitialized SRAM.
+pub fn flush() {
+    if !is_buffered() {
+        return;
+    }
+    let uart = debug::Uart {};
+    unsafe {
+        core::ptr::addr_of_mut!((*state()).draining).write_volatile(1);
+        while tail() != head() {
+            let tail = tail();
+            uart.putc(data().add(tail as usize & (LOG_LEN - 1)).read_volatile());
+            core::ptr::addr_of_mut!((*state()).tail).write_volatile(tail.wrapping_add(1));
+        }
+        core::ptr::addr_of_mut!((*state()).draining).write_volatile(0);
+    }
+}
+
+/// Drains as much of the ring as the DUART will take without waiting. Called from the trap
+/// handler, which cannot interrupt itself, so it only has to stay out of the way of `flush()`.
+pub fn pump() {
+    let uart = debug::Uart {};
+    unsafe {
+        if !is_buffered() || core::ptr::addr_of!((*state()).draining).read_volatile() != 0 {
+            return;
+        }
+        while tail() != head() {
+            let tail = tail();
+            if !uart.try_putc(data().add(tail as usize & (LOG_LEN - 1)).read_volatile()) {
+                break;
+            }
+            core::ptr::addr_of_mut!((*state()).tail).write_volatile(tail.wrapping_add(1));
+        }
+    }
+}
+
+/// Destination for `print!`: the ring while buffering is on, otherwise the DUART directly.
+/// Either way, the output is mirrored to the other sinks in `tracebuf` right away.
+pub struct Logger {}
+
+impl Write for Logger {
+    fn write_str(&mut self, s: &str) -> Result<(), Error> {
+        if is_buffered() {
+            crate::tracebuf::mirror(s.as_bytes());
+            for c in s.bytes() {
+                push(c);
+            }
+            Ok(())
+        } else {
+            debug::Uart {}.write_str(s)
+        }
+    }
+}
@@ -19,19 +19,30 @@ use utralib::generated::*;
 mod aes;
 mod bio;
 mod cam;
+mod check;
+mod console;
 mod debug;
+mod event;
+mod exit;
 mod gpio;
+mod harness;
 mod init;
 mod irqs;
+mod log;
+mod logbuf;
 mod mbox;
 #[cfg(feature = "pio")]
 mod pio;
 mod pl230;
 mod ramtests;
+mod results;
 mod rram;
 mod satp;
 mod sce;
+mod soak;
 mod timer0;
+mod tiny;
+mod tracebuf;
 mod udma;
 mod utils;
 
@@ -60,27 +71,77 @@ pub trait TestBoilerplate {
     fn passing_tests(&self) -> usize;
     fn set_enable(&mut self, ena: bool);
     fn is_enabled(&self) -> bool;
+    fn id(&self) -> harness::SuiteId;
+    fn cases(&self) -> &harness::CaseLog;
+    fn cases_mut(&mut self) -> &mut harness::CaseLog;
+    fn stats(&self) -> &harness::SuiteStats;
+    fn stats_mut(&mut self) -> &mut harness::SuiteStats;
+    /// Pass/fail as well as skipped and expected-fail outcomes, derived from the recorded cases
+    fn outcome(&self) -> harness::SuiteOutcome;
+    /// Mask of the `SuiteId`s that must have run and passed before this suite can run
+    fn requires(&self) -> u32;
 }
 
 /// Single method that is variable for each test
 pub trait TestRunner {
     fn run(&mut self);
+
+    /// Watchdog budget in ticktimer ticks; 0 runs the suite without a watchdog
+    fn budget_ticks(&self) -> u32 { harness::DEFAULT_BUDGET_TICKS }
+
+    /// Buffer `print!` output while the suite runs, and drain it afterwards (see `logbuf.rs`)
+    fn buffered_log(&self) -> bool { false }
 }
 
 trait Test: TestBoilerplate + TestRunner {}
 impl<T> Test for T where T: TestBoilerplate + TestRunner {}
 
-/// Macro for implementing all the test boilerplate
+/// Macro for implementing all the test boilerplate. Suites record their results as named
+/// cases in `self.cases`; the pass count is derived from those records.
+///
+/// Prerequisites are declared with a trailing `requires = [SuiteId, ...]`. The harness orders
+/// suites so that prerequisites run first, enables them automatically, and refuses to run a
+/// suite whose prerequisite failed.
+///
+/// Suites that should not run by default are constructed with `skipped(reason)`, and known-broken
+/// suites are marked with `expect_fail(reason)`; both are reported separately from pass/fail.
 #[macro_export]
 macro_rules! impl_test {
-    ($struct_name:ident, $test_name:expr, $test_count:ident) => {
+    ($struct_name:ident, $test_name:expr, $test_count:ident, $suite_id:ident) => {
+        $crate::impl_test!($struct_name, $test_name, $test_count, $suite_id, requires = []);
+    };
+    ($struct_name:ident, $test_name:expr, $test_count:ident, $suite_id:ident, requires = [$($req:ident),*]) => {
         pub struct $struct_name {
             name: &'static str,
-            passing_tests: usize,
             enabled: bool,
+            cases: $crate::harness::CaseLog,
+            stats: $crate::harness::SuiteStats,
         }
         impl $struct_name {
-            pub fn new(enabled: bool) -> Self { Self { name: $test_name, passing_tests: 0, enabled } }
+            pub fn new(enabled: bool) -> Self {
+                Self {
+                    name: $test_name,
+                    enabled,
+                    cases: $crate::harness::CaseLog::new(),
+                    stats: $crate::harness::SuiteStats::new(),
+                }
+            }
+
+            /// Creates the suite disabled, reporting it as skipped for `reason` unless it is
+            /// selected at runtime.
+            #[allow(dead_code)]
+            pub fn skipped(reason: &'static str) -> Self {
+                let mut suite = Self::new(false);
+                suite.cases.set_expectation($crate::harness::Expectation::Skip(reason));
+                suite
+            }
+
+            /// Marks the suite as known to fail, so that its failures don't fail the run.
+            #[allow(dead_code)]
+            pub fn expect_fail(mut self, reason: &'static str) -> Self {
+                self.cases.set_expectation($crate::harness::Expectation::Fail(reason));
+                self
+            }
         }
         impl TestBoilerplate for $struct_name {
             fn set_enable(&mut self, ena: bool) { self.enabled = ena }
@@ -89,9 +150,25 @@ macro_rules! impl_test {
 
             fn name(&self) -> &'static str { self.name }
 
-            fn passing_tests(&self) -> usize { self.passing_tests }
+            fn passing_tests(&self) -> usize { self.cases.passed() }
 
             fn total_tests(&self) -> usize { $test_count }
+
+            fn id(&self) -> $crate::harness::SuiteId { $crate::harness::SuiteId::$suite_id }
+
+            fn cases(&self) -> &$crate::harness::CaseLog { &self.cases }
+
+            fn cases_mut(&mut self) -> &mut $crate::harness::CaseLog { &mut self.cases }
+
+            fn stats(&self) -> &$crate::harness::SuiteStats { &self.stats }
+
+            fn stats_mut(&mut self) -> &mut $crate::harness::SuiteStats { &mut self.stats }
+
+            fn outcome(&self) -> $crate::harness::SuiteOutcome {
+                self.cases.outcome(self.stats.ran, $test_count)
+            }
+
+            fn requires(&self) -> u32 { 0 $(| $crate::harness::SuiteId::$req.mask())* }
         }
     };
 }
@@ -103,6 +180,9 @@ macro_rules! impl_test {
 #[export_name = "rust_entry"]
 pub unsafe extern "C" fn rust_entry(_unused1: *const usize, _unused2: u32) -> ! {
     early_init();
+    tracebuf::init();
+    // before anything that can panic, as the panic handler relies on the harness state
+    harness::init();
     let mut uart = debug::Uart {};
     uart.tiny_write_str("hello world!\r");
 
@@ -268,8 +348,11 @@ pub unsafe extern "C" fn rust_entry(_unused1: *const usize, _unused2: u32) -> !
     let mut timer0_tests = timer0::Timer0Tests::new(cfg!(feature = "timer0-tests"));
 
     let mut mbox_test = mbox::MboxTests::new(cfg!(feature = "mbox-tests"));
-    let mut rram_tests = rram::RramTests::new(cfg!(feature = "rram-tests"));
-    let mut rram_disturb_tests = rram::RramDisturbTests::new(cfg!(feature = "rram-tests"));
+    let mut rram_tests =
+        rram::RramTests::new(cfg!(feature = "rram-tests")).expect_fail("not passing right now");
+    // only runs if RramTests passes, as it checks the pattern RramTests leaves behind
+    let mut rram_disturb_tests = rram::RramDisturbTests::new(cfg!(feature = "rram-tests"))
+        .expect_fail("needs RRAM, which is not passing right now");
     let mut rram_lifecycle_tests = rram::RramLifecycle::new(cfg!(feature = "lifecycle-tests"));
     let mut udma_tests = udma::UdmaTests::new(cfg!(feature = "udma-tests"));
 
@@ -278,20 +361,22 @@ pub unsafe extern "C" fn rust_entry(_unused1: *const usize, _unused2: u32) -> !
     let mut cam_tests = cam::CamTests::new(cfg!(feature = "cam-tests"));
 
     // legacy tests - not run on NTO
-    let mut setup_uart2_test = init::SetupUart2Tests::new(false);
+    let mut setup_uart2_test = init::SetupUart2Tests::skipped("legacy, not run on NTO");
     #[cfg(feature = "pio")]
-    let mut pio_quick_tests = pio::PioQuickTests::new(false);
-    let mut byte_strobe_tests = ramtests::ByteStrobeTests::new(false);
-    let mut xip_tests = ramtests::XipTests::new(false);
-    let mut sce_dma_tests = sce::SceDmaTests::new(false);
+    let mut pio_quick_tests = pio::PioQuickTests::skipped("legacy, not run on NTO");
+    let mut byte_strobe_tests = ramtests::ByteStrobeTests::skipped("legacy, not run on NTO");
+    let mut xip_tests = ramtests::XipTests::skipped("legacy, not run on NTO");
+    let mut sce_dma_tests = sce::SceDmaTests::skipped("legacy, not run on NTO");
     let mut pl230_tests = pl230::Pl230Tests::new(cfg!(feature = "pl230-tests"));
 
+    // Registration order is the preferred run order. Prerequisites declared through `impl_test!`
+    // are enforced by the harness, which moves setup stages ahead of the suites that need them.
     let mut tests: [&mut dyn Test; 22] = [
         &mut reset_value_test,
         // stuff to run first
         &mut cam_tests,
         &mut rram_lifecycle_tests,
-        &mut rram_tests, // full-chip only, but run early - this isn't passing right now
+        &mut rram_tests, // full-chip only, but run early
         &mut gpio_tests,
         // quick tests
         &mut aes_tests,
@@ -303,7 +388,7 @@ pub unsafe extern "C" fn rust_entry(_unused1: *const usize, _unused2: u32) -> !
         &mut satp_setup,
         &mut irq_setup,
         // test core function
-        &mut satp_tests, // this relies on irq setup, so it can't be run right after satp setup
+        &mut satp_tests,
         &mut irq_tests,
         // irq-dependent tests
         &mut timer0_tests,
@@ -321,33 +406,60 @@ pub unsafe extern "C" fn rust_entry(_unused1: *const usize, _unused2: u32) -> !
         &mut rram_disturb_tests,
     ];
 
+    // cargo features only set the default mask; a selection table in SRAM can override it at boot
+    harness::apply_selection(&mut tests);
+
     #[cfg(feature = "apb-test")]
     apb_test();
 
-    for test in tests.iter_mut() {
-        if test.is_enabled() {
-            println!(">>> Running {}", test.name());
-            test.run();
-            println!("<<< {} done", test.name());
-        }
-    }
+    let passed = harness::run_suites(&mut tests);
+    soak::soak(&mut tests, passed);
+    console::console(&mut tests, passed);
 
     for test in tests.iter_mut() {
-        if test.is_enabled() {
-            println!("Test {}: {}/{} passing", test.name(), test.passing_tests(), test.total_tests());
+        let outcome = test.outcome();
+        if outcome == harness::SuiteOutcome::NotRun {
+            continue;
+        }
+        print!("Test {}: {}/{} passing", test.name(), test.passing_tests(), test.total_tests());
+        let stats = test.stats();
+        if stats.ran {
+            print!(
+                " in {} ticks ({}..{}), {} cycles",
+                stats.elapsed_ticks(),
+                stats.start_ticks,
+                stats.end_ticks,
+                stats.cycles
+            );
+        }
+        match outcome.reason() {
+            Some(reason) => println!(", {}: {}", outcome.tag(), reason),
+            None => println!(),
+        }
+        if let Some((file, line)) = stats.panic {
+            println!("  PANIC {} at {}:{}", test.name(), file, line);
+        }
+        if (harness::select_flags() & harness::FLAG_CASE_TIMING) != 0 {
+            for case in test.cases().iter() {
+                println!("  case {}: {} ticks, {} cycles", case.name, case.ticks, case.cycles);
+            }
+        }
+        for case in test.cases().failures() {
+            println!("  FAIL {}::{}: {} ({}/{})", test.name(), case.name, case.msg, case.passed, case.total);
+        }
+        for case in test.cases().expected_failures() {
+            println!("  XFAIL {}::{}: {} ({}/{})", test.name(), case.name, case.msg, case.passed, case.total);
+        }
+        if test.cases().dropped() != 0 {
+            println!("  ({} further cases not itemized)", test.cases().dropped());
         }
     }
 
+    results::emit(&tests);
+
     println!("Tests done.");
-    // this triggers the simulation to end using a sim-only verilog hook
-    let mut report = CSR::new(utra::main::HW_MAIN_BASE as *mut u32);
-    report.wfo(utra::main::DONE_DONE, 1);
-    // this sequence triggers an end of simulation on s32
-    let mut test_cfg = CSR::new(utra::csrtest::HW_CSRTEST_BASE as *mut u32);
-    test_cfg.wo(utra::csrtest::WTEST, 0xc0ded02e);
-    test_cfg.wo(utra::csrtest::WTEST, 0xc0de600d);
-
-    loop {}
+    let (status, failures) = exit::run_status(&tests);
+    exit::end_run(status, failures);
 }
 
 #[cfg(target_os = "none")]
@@ -357,17 +469,16 @@ mod panic_handler {
 
     #[panic_handler]
     fn handle_panic(arg: &PanicInfo) -> ! {
+        // get anything buffered out first, and don't buffer the panic report
+        crate::logbuf::set_buffered(false);
         crate::println!("{}", arg);
         if let Some(location) = arg.location() {
             crate::println!("At '{}'@{}", location.file(), location.line(),);
         }
-        // exit the simulation
-        let mut test_cfg = utralib::CSR::new(utralib::utra::csrtest::HW_CSRTEST_BASE as *mut u32);
-        test_cfg.wo(utralib::utra::csrtest::WTEST, 0xc0ded02e);
-        test_cfg.wo(utralib::utra::csrtest::WTEST, 0xc0de600d);
-        let mut report = utralib::CSR::new(utralib::utra::main::HW_MAIN_BASE as *mut u32);
-        report.wfo(utralib::utra::main::DONE_DONE, 1);
-        loop {}
+        // abandons the running suite and carries on with the next one, if there is one
+        crate::harness::panic_resume(arg.location());
+        // exit the simulation; the panic counts as a failure on top of any suites that had failed
+        crate::exit::end_run(crate::exit::ExitStatus::Panic, crate::harness::failures() + 1)
     }
 }
 
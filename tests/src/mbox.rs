@@ -11,28 +11,30 @@
 
 use core::convert::TryFrom;
 
+use tests::mbox_frame;
 use utra::mailbox;
 use utralib::generated::*;
 
 use crate::*;
 
 const MBOX_TESTS: usize = 3;
-crate::impl_test!(MboxTests, "MBOX", MBOX_TESTS);
+// requires IrqSetup so that the watchdog bounds the polling of a CM7 that never answers
+crate::impl_test!(MboxTests, "MBOX", MBOX_TESTS, Mbox, requires = [IrqSetup]);
 impl TestRunner for MboxTests {
     fn run(&mut self) {
         for i in 0..2 {
             crate::println!("waiting {}\n", i);
         }
 
-        // each of these tests auto-incs the passed field
-        self.knock();
+        // each of these tests records its own case result
+        self.knock("knock");
         self.abort();
-        self.knock();
+        self.knock("knock_after_abort");
     }
 }
 
 impl MboxTests {
-    pub fn knock(&mut self) {
+    pub fn knock(&mut self, case: &'static str) {
         let mut mbox = Mbox::new();
 
         let test_data = [0xC0DE_0000u32, 0x0000_600Du32, 0, 0, 0, 0, 0, 0];
@@ -42,10 +44,10 @@ impl MboxTests {
         }
         let test_pkt =
             MboxToCm7Pkt { version: MBOX_PROTOCOL_REV, opcode: ToCm7Op::Knock, len: 2, data: test_data };
-        // crate::println!("sending knock...\n");
+        crate::trace!("sending knock...");
         match mbox.try_send(test_pkt) {
             Ok(_) => {
-                // crate::println!("Packet send Ok\n");
+                crate::trace!("Packet send Ok");
                 let mut timeout = 0;
                 while mbox.poll_not_ready() {
                     timeout += 1;
@@ -54,16 +56,19 @@ impl MboxTests {
                     }
                     if timeout >= 10_000 {
                         crate::println!("Mbox timed out");
+                        self.cases.fail(case, "timed out waiting for response");
                         return;
                     }
                 }
                 // now receive the packet
-                // crate::println!("try_rx()...");
+                crate::trace!("try_rx()...");
                 match mbox.try_rx() {
                     Ok(rx_pkt) => {
                         crate::println!("Knock result: {:x}", rx_pkt.data[0]);
                         if rx_pkt.version != MBOX_PROTOCOL_REV {
                             crate::println!("Version mismatch {} != {}", rx_pkt.version, MBOX_PROTOCOL_REV);
+                            self.cases.fail(case, "version mismatch");
+                            return;
                         }
                         if rx_pkt.opcode != ToRvOp::RetKnock {
                             crate::println!(
@@ -71,29 +76,25 @@ impl MboxTests {
                                 rx_pkt.opcode as u16,
                                 ToRvOp::RetKnock as u16
                             );
+                            self.cases.fail(case, "opcode mismatch");
+                            return;
                         }
                         if rx_pkt.len != 1 {
                             crate::println!("Expected length mismatch {} != {}", rx_pkt.len, 1);
-                        } else {
-                            if rx_pkt.data[0] != expected_result {
-                                crate::println!(
-                                    "Expected data mismatch {:x} != {:x}",
-                                    rx_pkt.data[0],
-                                    expected_result
-                                );
-                            } else {
-                                crate::println!("Knock test PASS: {:x}", rx_pkt.data[0]);
-                                self.passing_tests += 1;
-                            }
+                            self.cases.fail(case, "length mismatch");
+                        } else if crate::check_eq!(self.cases, case, rx_pkt.data[0], expected_result) {
+                            crate::println!("Knock test PASS: {:x}", rx_pkt.data[0]);
                         }
                     }
                     Err(e) => {
                         crate::println!("Error while deserializing: {:?}\n", e);
+                        self.cases.fail(case, "could not deserialize response");
                     }
                 }
             }
             Err(e) => {
                 crate::println!("Packet send error: {:?}\n", e);
+                self.cases.fail(case, "packet send error");
             }
         };
     }
@@ -103,16 +104,15 @@ impl MboxTests {
         match mbox.abort() {
             Ok(_) => {
                 crate::println!("Abort OK");
-                self.passing_tests += 1
+                self.cases.pass("abort");
             }
             Err(e) => {
                 crate::println!("Abort test failed with {:?}", e);
+                self.cases.fail("abort", "abort returned an error");
             }
         }
     }
 }
-/// This constraint is limited by the size of the memory on the CM7 side
-const MAX_PKT_LEN: usize = 128;
 const MBOX_PROTOCOL_REV: u32 = 0;
 const TX_FIFO_DEPTH: u32 = 128;
 
@@ -197,18 +197,15 @@ impl Mbox {
         // clear any pending bits from previous transactions
         self.csr.wo(mailbox::EV_PENDING, self.csr.r(mailbox::EV_PENDING));
 
-        if to_cm7.len > MAX_PKT_LEN {
-            Err(MboxError::TxOverflow)
-        } else {
-            self.expect_tx(to_cm7.version)?;
-            self.expect_tx(to_cm7.opcode as u32 | (to_cm7.len as u32) << 16)?;
-            for &d in to_cm7.data[..to_cm7.len].iter() {
-                self.expect_tx(d)?;
-            }
-            // trigger the send
-            self.csr.wfo(mailbox::DONE_DONE, 1);
-            Ok(())
+        let data = to_cm7.data.get(..to_cm7.len).ok_or(MboxError::TxOverflow)?;
+        let words =
+            mbox_frame::frame(to_cm7.version, to_cm7.opcode as u16, data).ok_or(MboxError::TxOverflow)?;
+        for word in words {
+            self.expect_tx(word)?;
         }
+        // trigger the send
+        self.csr.wfo(mailbox::DONE_DONE, 1);
+        Ok(())
     }
 
     fn expect_rx(&mut self) -> Result<u32, MboxError> {
@@ -221,9 +218,8 @@ impl Mbox {
 
     pub fn try_rx(&mut self) -> Result<MboxToRvPkt, MboxError> {
         let version = self.expect_rx()?;
-        let op_and_len = self.expect_rx()?;
-        let opcode = ToRvOp::try_from((op_and_len & 0xFFFF) as u16)?;
-        let len = (op_and_len >> 16) as usize;
+        let (opcode, len) = mbox_frame::parse_header(self.expect_rx()?);
+        let opcode = ToRvOp::try_from(opcode)?;
         let mut data = [0u32; STATIC_DATA_LEN];
         for d in data[..len.min(STATIC_DATA_LEN)].iter_mut() {
             *d = self.expect_rx()?;
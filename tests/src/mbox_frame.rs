@@ -0,0 +1,81 @@
+// (c) Copyright CrossBar, Inc. 2024.
+//
+// This documentation describes Open Hardware and is licensed under the
+// [CERN-OHL-W-2.0].
+//
+// You may redistribute and modify this documentation under the terms of the
+// [CERN-OHL- W-2.0 (http://ohwr.org/cernohl)]. This documentation is
+// distributed WITHOUT ANY EXPRESS OR IMPLIED WARRANTY, INCLUDING OF
+// MERCHANTABILITY, SATISFACTORY QUALITY AND FITNESS FOR A PARTICULAR PURPOSE.
+// Please see the [CERN-OHL- W-2.0] for applicable conditions.
+
+//! Framing of the packets `mbox.rs` exchanges with the CM7.
+//!
+//! A packet is a protocol version word, then a header word with the opcode in the low half and
+//! the number of data words in the high half, then the data words.
+
+/// This constraint is limited by the size of the memory on the CM7 side
+pub const MAX_PKT_LEN: usize = 128;
+
+/// Packs an opcode and data length into a header word.
+pub fn header(opcode: u16, len: usize) -> u32 { opcode as u32 | (len as u32) << 16 }
+
+/// Splits a header word into its opcode and data length.
+pub fn parse_header(word: u32) -> (u16, usize) { ((word & 0xFFFF) as u16, (word >> 16) as usize) }
+
+/// The words of a packet in the order they go into the mailbox, or `None` if `data` is longer
+/// than `MAX_PKT_LEN`.
+pub fn frame(version: u32, opcode: u16, data: &[u32]) -> Option<impl Iterator<Item = u32> + '_> {
+    if data.len() > MAX_PKT_LEN {
+        return None;
+    }
+    let header = header(opcode, data.len());
+    Some(core::iter::once(version).chain(core::iter::once(header)).chain(data.iter().copied()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_layout() {
+        assert_eq!(header(1, 2), 0x0002_0001);
+        assert_eq!(header(0x81, 0), 0x0000_0081);
+        assert_eq!(header(0xffff, MAX_PKT_LEN), 0x0080_ffff);
+    }
+
+    #[test]
+    fn header_round_trip() {
+        for &(opcode, len) in &[(0, 0), (1, 2), (128, 1), (130, MAX_PKT_LEN), (0xffff, 0xffff)] {
+            assert_eq!(parse_header(header(opcode, len)), (opcode, len));
+        }
+    }
+
+    #[test]
+    fn frame_order() {
+        let words: Vec<u32> = frame(0, 1, &[0xdead_beef, 0x1234_5678]).unwrap().collect();
+        assert_eq!(words, vec![0, 0x0002_0001, 0xdead_beef, 0x1234_5678]);
+    }
+
+    #[test]
+    fn empty_frame() {
+        let words: Vec<u32> = frame(7, 3, &[]).unwrap().collect();
+        assert_eq!(words, vec![7, 0x0000_0003]);
+    }
+
+    #[test]
+    fn frame_length_limit() {
+        let data = [0u32; MAX_PKT_LEN + 1];
+        assert_eq!(frame(0, 1, &data[..MAX_PKT_LEN]).unwrap().count(), MAX_PKT_LEN + 2);
+        assert!(frame(0, 1, &data).is_none());
+    }
+
+    #[test]
+    fn frame_parses_back() {
+        let data = [1, 2, 3];
+        let words: Vec<u32> = frame(0, 2, &data).unwrap().collect();
+        let (opcode, len) = parse_header(words[1]);
+        assert_eq!(opcode, 2);
+        assert_eq!(&words[2..2 + len], &data);
+    }
+}
@@ -13,11 +13,12 @@ use utralib::generated::*;
 use xous_pio::pio_tests::spi::*;
 use xous_pio::*;
 
+use crate::event::{self, Event, Subsystem};
 use crate::utils::*;
 use crate::{TestBoilerplate, TestRunner};
 
 const TOTAL_QUICK_TESTS: usize = 2;
-crate::impl_test!(PioQuickTests, "PIO Quick", TOTAL_QUICK_TESTS);
+crate::impl_test!(PioQuickTests, "PIO Quick", TOTAL_QUICK_TESTS, PioQuick, requires = [SatpSetup, IrqSetup]);
 
 impl TestRunner for PioQuickTests {
     fn run(&mut self) {
@@ -36,7 +37,7 @@ impl TestRunner for PioQuickTests {
 
         let mut pio_csr = CSR::new(utra::rp_pio::HW_RP_PIO_BASE as *mut u32);
 
-        report_api(0x0D10_05D1);
+        Subsystem::Pio.report(Event::Begin, &[]);
 
         let mut pio_ss = PioSharedState::new();
         let mut pio_sm = pio_ss.alloc_sm().unwrap();
@@ -52,9 +53,9 @@ impl TestRunner for PioQuickTests {
             "in pins, 1  side 0"      // Input data, deassert SCK
         );
         let prog_cpha0 = LoadedProg::load(spi_cpha0_prog.program, &mut pio_ss).unwrap();
-        report_api(0x05D1_0000);
+        Subsystem::Pio.report(Event::Step(0), &[]);
         let prog_cpha1 = LoadedProg::load(spi_cpha1_prog.program, &mut pio_ss).unwrap();
-        report_api(0x05D1_0001);
+        Subsystem::Pio.report(Event::Step(1), &[]);
 
         let clkdiv: f32 = 137.25;
         let mut passing = 0;
@@ -63,7 +64,7 @@ impl TestRunner for PioQuickTests {
         pio_csr.wo(utra::rp_pio::SFR_IRQ1_INTE, (pio_sm.sm_bitmask()) << 4);
         loop {
             // pha = 1
-            report_api(0x05D1_0002);
+            Subsystem::Pio.report(Event::Step(2), &[]);
             pio_spi_init(
                 &mut pio_sm,
                 &prog_cpha0, // cpha set here
@@ -74,13 +75,13 @@ impl TestRunner for PioQuickTests {
                 PIN_MOSI,
                 PIN_MISO,
             );
-            report_api(0x05D1_0003);
+            Subsystem::Pio.report(Event::Step(3), &[]);
             if spi_test_core_boot(&mut pio_sm) {
                 passing += 1;
             };
 
             // pha = 0
-            report_api(0x05D1_0004);
+            Subsystem::Pio.report(Event::Step(4), &[]);
             pio_spi_init(
                 &mut pio_sm,
                 &prog_cpha1, // cpha set here
@@ -91,7 +92,7 @@ impl TestRunner for PioQuickTests {
                 PIN_MOSI,
                 PIN_MISO,
             );
-            report_api(0x05D1_0005);
+            Subsystem::Pio.report(Event::Step(5), &[]);
             if spi_test_core_boot(&mut pio_sm) {
                 passing += 1;
             };
@@ -107,12 +108,12 @@ impl TestRunner for PioQuickTests {
         pio_csr.wo(utra::rp_pio::SFR_IRQ1_INTE, 0);
         pio_csr.wo(utra::rp_pio::SFR_SYNC_BYPASS, 0);
 
-        self.passing_tests = passing;
+        self.cases.tally("quick", passing, TOTAL_QUICK_TESTS);
     }
 }
 
 pub fn spi_test_core_boot(pio_sm: &mut PioSm) -> bool {
-    report_api(0x0D10_05D1);
+    Subsystem::Pio.report(Event::Step(event::pio::TRANSFER), &[]);
 
     const BUF_SIZE: usize = 20;
     let mut state: u16 = 0xAF;
@@ -122,22 +123,22 @@ pub fn spi_test_core_boot(pio_sm: &mut PioSm) -> bool {
     for d in tx_buf.iter_mut() {
         state = crate::utils::lfsr_next(state);
         *d = state as u8;
-        report_api(*d as u32);
+        Subsystem::Pio.report(Event::Value(event::pio::TX), &[*d as u32]);
     }
     pio_spi_write8_read8_blocking(pio_sm, &tx_buf, &mut rx_buf);
     let mut pass = true;
     for (&s, &d) in tx_buf.iter().zip(rx_buf.iter()) {
         if s != d {
-            report_api(0xDEAD_0000 | (s as u32) << 8 | ((d as u32) << 0));
+            Subsystem::Pio.report(Event::Fail(event::pio::MISMATCH), &[s as u32, d as u32]);
             pass = false;
         }
     }
-    report_api(0x600D_05D1);
+    Subsystem::Pio.report(Event::End, &[]);
     pass
 }
 
 const PIO_TESTS: usize = 1;
-crate::impl_test!(PioTests, "PIO extended", PIO_TESTS);
+crate::impl_test!(PioTests, "PIO extended", PIO_TESTS, Pio);
 impl TestRunner for PioTests {
     fn run(&mut self) {
         xous_pio::pio_tests::setup_reporting(
@@ -146,6 +147,6 @@ impl TestRunner for PioTests {
 
         // TODO: break this into subtest count
         xous_pio::pio_tests::pio_tests();
-        self.passing_tests += 1;
+        self.cases.pass("pio_tests");
     }
 }
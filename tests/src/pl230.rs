@@ -12,7 +12,7 @@
 use crate::*;
 
 const PL230_TESTS: usize = 1;
-crate::impl_test!(Pl230Tests, "PL230", PL230_TESTS);
+crate::impl_test!(Pl230Tests, "PL230", PL230_TESTS, Pl230, requires = [SatpSetup, IrqSetup]);
 impl TestRunner for Pl230Tests {
     fn run(&mut self) {
         // setup IOs for the PL230 test outputs
@@ -25,6 +25,6 @@ impl TestRunner for Pl230Tests {
             iox_csr.add(0x200 / core::mem::size_of::<u32>()).write_volatile(0xffffffff); // PIO sel port D31-0
         }
         xous_pl230::pl230_tests::pl230_tests();
-        self.passing_tests += 1;
+        self.cases.pass("pl230_tests");
     }
 }
@@ -0,0 +1,205 @@
+// (c) Copyright CrossBar, Inc. 2024.
+//
+// This documentation describes Open Hardware and is licensed under the
+// [CERN-OHL-W-2.0].
+//
+// You may redistribute and modify this documentation under the terms of the
+// [CERN-OHL- W-2.0 (http://ohwr.org/cernohl)]. This documentation is
+// distributed WITHOUT ANY EXPRESS OR IMPLIED WARRANTY, INCLUDING OF
+// MERCHANTABILITY, SATISFACTORY QUALITY AND FITNESS FOR A PARTICULAR PURPOSE.
+// Please see the [CERN-OHL- W-2.0] for applicable conditions.
+
+//! PLL divider math for `init::init_clock_asic()`.
+/*
+   Code notes from RTL:
+   assign pll_m = ipc_pllmn[16:12];
+   assign pll_n = ipc_pllmn[11: 0];
+   assign pll_f = ipc_pllf[23: 0];
+   assign pll_fen = ipc_pllf[24];
+   assign pll_q00 = ipc_pllq[ 2: 0];
+   assign pll_q10 = ipc_pllq[ 6: 4];
+   assign pll_q01 = ipc_pllq[10: 8];
+   assign pll_q11 = ipc_pllq[14:12];
+
+   Clko0 = Fvco / (pllq[ 2:0] + 1) / (pllq[ 6:4] + 1)
+   Clko1 = Fvco / (pllq[10:8] + 1) / (pllq[14:12] + 1)
+   Fvco target is 2GHz (1-3GHz range)
+
+  .gvco_bias ( pll_bias[7:6] ),
+  .cpp_bias  ( pll_bias[5:3] ),
+  .cpi_bias  ( pll_bias[2:0] ),
+*/
+
+const TARGET_VCO_HZ: u32 = 1_600_000_000; // 1.6GHz
+const TARGET_PERCLK_HZ: u32 = 100_000_000; // 100 MHz
+/// Reference clock into the PLL
+pub const FREF_HZ: u32 = 48_000_000;
+/// adjust m so that PFD runs between 4-16MHz (target 8MHz)
+pub const PREDIV_M: u32 = 6;
+/// PFD frequency
+pub const PFD_HZ: u32 = FREF_HZ / PREDIV_M;
+const _: () = assert!(PFD_HZ == 8_000_000);
+
+/// Rounds `div` up to a power of 2.
+fn pow2_div(div: u32) -> u32 { if (1 << div.ilog2()) != div { 1 << (div.ilog2() + 1) } else { div } }
+
+/// PLL settings that get fclk as close as possible to a target frequency.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PllConfig {
+    /// VCO frequency actually used
+    pub vco_hz: u32,
+    /// Division from the VCO to fclk; a power of 2
+    pub fclk_div: u32,
+    /// Division from the VCO to perclk; a power of 2
+    pub perclk_div: u32,
+    /// Post-divider settings `[q0_0, q1_0, q0_1, q1_1]`, each one less than its divisor
+    pub q: [u32; 4],
+    /// Integer part of the feedback divider
+    pub ni: u32,
+    /// Fractional part of the feedback divider, in 1/2^24ths
+    pub pllf: u32,
+}
+
+impl PllConfig {
+    /// Derives VCO frequency from legal, even dividers that get us close to `freq_hz`.
+    pub fn new(freq_hz: u32) -> Self {
+        let final_div: u32 = TARGET_VCO_HZ / freq_hz;
+        // fclk_div has to be a power of 2
+        let fclk_div = pow2_div(final_div);
+        let vco_hz: u32 = fclk_div * freq_hz;
+        let perclk_div = pow2_div(vco_hz / TARGET_PERCLK_HZ);
+
+        // split each power-of-2 division as evenly as possible across its pair of post-dividers
+        let ilog2_fdiv = fclk_div.ilog2();
+        let ilog2_pdiv = perclk_div.ilog2();
+        let q = [
+            (1 << (ilog2_fdiv / 2)) - 1,
+            (1 << (ilog2_fdiv / 2 + ilog2_fdiv % 2)) - 1,
+            (1 << (ilog2_pdiv / 2)) - 1,
+            (1 << (ilog2_pdiv / 2 + ilog2_pdiv % 2)) - 1,
+        ];
+
+        // now, program the VCO to get to as close to vco_hz
+        let ni = vco_hz / PFD_HZ;
+        let frac_n = ((vco_hz as f32 / PFD_HZ as f32) - ni as f32).max(0 as f32);
+        let pllf: u32 = (frac_n * ((1 << 24) as f32)) as u32;
+        PllConfig { vco_hz, fclk_div, perclk_div, q, ni, pllf }
+    }
+
+    /// The `SFR_IPCPLLQ` value
+    pub fn pllq(&self) -> u32 {
+        let [q0_0, q1_0, q0_1, q1_1] = self.q;
+        (q0_0 & 7) | ((q1_0 & 7) << 4) | ((q0_1 & 7) << 8) | ((q1_1 & 7) << 12)
+    }
+
+    /// The `SFR_IPCPLLMN` value: m is set to `PREDIV_M`, lower 12 bits is nf
+    pub fn pllmn(&self) -> u32 { (PREDIV_M << 12) | self.ni & 0xFFF }
+
+    /// The `SFR_IPCPLLF` value, with the frac enable bit set if needed
+    pub fn n_frac(&self) -> u32 { if self.pllf != 0 { self.pllf | 1 << 24 } else { 0 } }
+
+    /// The resulting fclk
+    pub fn fclk_hz(&self) -> u32 { self.vco_hz / self.fclk_div }
+
+    /// The resulting perclk
+    pub fn perclk_hz(&self) -> u32 { self.vco_hz / self.perclk_div }
+
+    pub fn vco_in_range(&self) -> bool { self.vco_hz >= 1_000_000_000 && self.vco_hz <= 3_000_000_000 }
+
+    pub fn q_in_range(&self) -> bool { self.q.iter().all(|&q| q <= 7) }
+
+    pub fn ni_in_range(&self) -> bool { self.ni >= 8 && self.ni < 4096 }
+
+    pub fn pllf_in_range(&self) -> bool { self.pllf < 1 << 24 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// fclk settings the firmware has used
+    const FREQS: [u32; 5] = [800_000_000, 700_000_000, 400_000_000, 350_000_000, 100_000_000];
+
+    #[test]
+    fn config_800mhz() {
+        let pll = PllConfig::new(800_000_000);
+        assert_eq!(pll.vco_hz, 1_600_000_000);
+        assert_eq!(pll.fclk_div, 2);
+        assert_eq!(pll.perclk_div, 16);
+        assert_eq!(pll.q, [0, 1, 3, 3]);
+        assert_eq!(pll.pllq(), 0x3310);
+        assert_eq!(pll.pllmn(), 0x60c8);
+        assert_eq!(pll.n_frac(), 0);
+        assert_eq!(pll.perclk_hz(), 100_000_000);
+    }
+
+    #[test]
+    fn config_350mhz() {
+        let pll = PllConfig::new(350_000_000);
+        assert_eq!(pll.vco_hz, 1_400_000_000);
+        assert_eq!(pll.fclk_div, 4);
+        // 14 rounds up to 16
+        assert_eq!(pll.perclk_div, 16);
+        assert_eq!(pll.q, [1, 1, 3, 3]);
+        assert_eq!(pll.ni, 175);
+        assert_eq!(pll.perclk_hz(), 87_500_000);
+    }
+
+    #[test]
+    fn dividers_are_powers_of_two() {
+        for &f in FREQS.iter() {
+            let pll = PllConfig::new(f);
+            assert!(pll.fclk_div.is_power_of_two(), "{} Hz: fclk_div {}", f, pll.fclk_div);
+            assert!(pll.perclk_div.is_power_of_two(), "{} Hz: perclk_div {}", f, pll.perclk_div);
+        }
+    }
+
+    #[test]
+    fn post_dividers_multiply_out() {
+        for &f in FREQS.iter() {
+            let pll = PllConfig::new(f);
+            let [q0_0, q1_0, q0_1, q1_1] = pll.q;
+            assert_eq!((q0_0 + 1) * (q1_0 + 1), pll.fclk_div, "{} Hz", f);
+            assert_eq!((q0_1 + 1) * (q1_1 + 1), pll.perclk_div, "{} Hz", f);
+            // the register fields decode back to the same dividers
+            let pllq = pll.pllq();
+            let clko0 = pll.vco_hz / ((pllq & 7) + 1) / (((pllq >> 4) & 7) + 1);
+            let clko1 = pll.vco_hz / (((pllq >> 8) & 7) + 1) / (((pllq >> 12) & 7) + 1);
+            assert_eq!(clko0, f, "{} Hz", f);
+            assert_eq!(clko1, pll.perclk_hz(), "{} Hz", f);
+        }
+    }
+
+    #[test]
+    fn settings_in_range() {
+        for &f in FREQS.iter() {
+            let pll = PllConfig::new(f);
+            assert!(pll.vco_in_range(), "{} Hz: vco {}", f, pll.vco_hz);
+            assert!(pll.q_in_range(), "{} Hz: q {:?}", f, pll.q);
+            assert!(pll.ni_in_range(), "{} Hz: ni {}", f, pll.ni);
+            assert!(pll.pllf_in_range(), "{} Hz: pllf {:#x}", f, pll.pllf);
+            assert_eq!(pll.fclk_hz(), f);
+            assert!(pll.perclk_hz() <= TARGET_PERCLK_HZ, "{} Hz: perclk {}", f, pll.perclk_hz());
+        }
+    }
+
+    #[test]
+    fn vco_is_fed_back_exactly() {
+        for &f in FREQS.iter() {
+            let pll = PllConfig::new(f);
+            let fvco = PFD_HZ as u64 * pll.ni as u64 + ((PFD_HZ as u64 * pll.pllf as u64) >> 24);
+            assert_eq!(fvco, pll.vco_hz as u64, "{} Hz", f);
+            assert_eq!(pll.pllmn() >> 12, PREDIV_M);
+            assert_eq!(pll.pllmn() & 0xfff, pll.ni);
+        }
+    }
+
+    #[test]
+    fn fractional_feedback() {
+        // 1.5GHz is not a multiple of the 8MHz PFD: 187.5
+        let pll = PllConfig::new(750_000_000);
+        assert_eq!(pll.ni, 187);
+        assert_eq!(pll.pllf, 1 << 23);
+        assert_eq!(pll.n_frac(), 1 << 24 | 1 << 23);
+    }
+}
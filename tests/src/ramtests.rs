@@ -15,58 +15,66 @@ use core::mem::size_of;
 
 use utralib::generated::*;
 
+use crate::event::{self, Event, Subsystem};
 use crate::satp;
 use crate::utils::*;
 use crate::TestRunner;
 use crate::*;
 
 const BYTE_STROBE_TESTS: usize = 1;
-crate::impl_test!(ByteStrobeTests, "Byte strobes", BYTE_STROBE_TESTS);
+crate::impl_test!(
+    ByteStrobeTests,
+    "Byte strobes",
+    BYTE_STROBE_TESTS,
+    ByteStrobe,
+    requires = [SatpSetup, IrqSetup]
+);
 impl TestRunner for ByteStrobeTests {
     fn run(&mut self) {
         unsafe {
             check_byte_strobes();
         }
-        self.passing_tests += 1;
+        self.cases.pass("byte_strobes");
     }
 }
 
 const RAM_TESTS: usize = 8;
-crate::impl_test!(RamTests, "RAM", RAM_TESTS);
+// tests the RAM above the page tables that satp_setup() builds
+crate::impl_test!(RamTests, "RAM", RAM_TESTS, Ram, requires = [SatpSetup, IrqSetup]);
 impl TestRunner for RamTests {
     fn run(&mut self) {
-        self.passing_tests += unsafe { caching_tests() };
+        self.cases.tally("caching", unsafe { caching_tests() }, 1);
 
         const BASE_ADDR: u32 = satp::PT_LIMIT as u32; // don't overwrite our PT data
         unsafe {
             // 'random' access test
             let mut test_slice = core::slice::from_raw_parts_mut(BASE_ADDR as *mut u32, 512);
-            self.passing_tests += ramtest_lfsr(&mut test_slice, 3);
+            self.cases.tally("lfsr_u32", ramtest_lfsr(&mut test_slice, 3), 1);
 
             // now some basic memory read/write tests
             // entirely within cache access test
             // 256-entry by 32-bit slice at start of RAM
             let mut test_slice = core::slice::from_raw_parts_mut(BASE_ADDR as *mut u32, 256);
-            self.passing_tests += ramtest_all(&mut test_slice, 4);
+            self.cases.tally("all_u32", ramtest_all(&mut test_slice, 4), 1);
             // byte access test
             let mut test_slice = core::slice::from_raw_parts_mut(BASE_ADDR as *mut u8, 256);
-            self.passing_tests += ramtest_fast(&mut test_slice, 5);
+            self.cases.tally("fast_u8", ramtest_fast(&mut test_slice, 5), 1);
             // word access test
             let mut test_slice = core::slice::from_raw_parts_mut(BASE_ADDR as *mut u16, 512);
-            self.passing_tests += ramtest_fast(&mut test_slice, 6); // 1ff00
+            self.cases.tally("fast_u16", ramtest_fast(&mut test_slice, 6), 1); // 1ff00
 
             // outside cache test
             // 6144-entry by 32-bit slice at start of RAM - should cross outside cache boundary
             let mut test_slice = core::slice::from_raw_parts_mut(BASE_ADDR as *mut u32, 0x1800);
-            self.passing_tests += ramtest_fast(&mut test_slice, 7); // c7f600
+            self.cases.tally("fast_u32_uncached", ramtest_fast(&mut test_slice, 7), 1); // c7f600
 
             // this passed, now that the AXI state machine is fixed.
             let mut test_slice = core::slice::from_raw_parts_mut(BASE_ADDR as *mut u32, 0x1800);
-            self.passing_tests += ramtest_fast_specialcase1(&mut test_slice, 8); // c7f600
+            self.cases.tally("specialcase1", ramtest_fast_specialcase1(&mut test_slice, 8), 1); // c7f600
 
             // u64 access test
             let mut test_slice = core::slice::from_raw_parts_mut(BASE_ADDR as *mut u64, 0xC00);
-            self.passing_tests += ramtest_fast(&mut test_slice, 9);
+            self.cases.tally("fast_u64", ramtest_fast(&mut test_slice, 9), 1);
 
             // random size/access test
             // let mut test_slice = core::slice::from_raw_parts_mut(BASE_ADDR as *mut u8, 0x6000);
@@ -75,9 +83,10 @@ impl TestRunner for RamTests {
 }
 
 const XIP_TESTS: usize = 16;
-crate::impl_test!(XipTests, "XIP", XIP_TESTS);
+// XIP_VA is only mapped by satp_setup()
+crate::impl_test!(XipTests, "XIP", XIP_TESTS, Xip, requires = [SatpSetup]);
 impl TestRunner for XipTests {
-    fn run(&mut self) { self.passing_tests += xip_test(); }
+    fn run(&mut self) { self.cases.tally("xip", xip_test(), XIP_TESTS); }
 }
 
 pub unsafe fn check_byte_strobes() {
@@ -97,55 +106,55 @@ pub unsafe fn check_byte_strobes() {
 }
 pub unsafe fn caching_tests() -> usize {
     // test of the 0x500F cache flush instruction - this requires manual inspection of the report values
-    report_api(0x000c_ac7e);
+    Subsystem::Cache.report(Event::Begin, &[]);
     const CACHE_WAYS: usize = 4;
     const CACHE_SET_SIZE: usize = 4096 / size_of::<u32>();
     let test_slice = core::slice::from_raw_parts_mut(satp::PT_LIMIT as *mut u32, CACHE_SET_SIZE * CACHE_WAYS);
     // bottom of cache
     for set in 0..4 {
-        report_api((&mut test_slice[set * CACHE_SET_SIZE] as *mut u32) as u32);
+        let addr = (&mut test_slice[set * CACHE_SET_SIZE] as *mut u32) as u32;
+        Subsystem::Cache.report(Event::Value(event::cache::ADDR), &[addr]);
         (&mut test_slice[set * CACHE_SET_SIZE] as *mut u32).write_volatile(0x0011_1111 * (1 + set as u32));
     }
     // top of cache
     for set in 0..4 {
-        report_api((&mut test_slice[set * CACHE_SET_SIZE + CACHE_SET_SIZE - 1] as *mut u32) as u32);
+        let addr = (&mut test_slice[set * CACHE_SET_SIZE + CACHE_SET_SIZE - 1] as *mut u32) as u32;
+        Subsystem::Cache.report(Event::Value(event::cache::ADDR), &[addr]);
         (&mut test_slice[set * CACHE_SET_SIZE + CACHE_SET_SIZE - 1] as *mut u32)
             .write_volatile(0x1100_2222 * (1 + set as u32));
     }
     // read cached values - first iteration populates the cache; second iteration should be cached
     for iter in 0..2 {
-        report_api(0xb1d0_0000 + iter + 1);
+        Subsystem::Cache.report(Event::Step(event::cache::PRE_FLUSH + iter + 1), &[]);
         for set in 0..4 {
             let a = (&mut test_slice[set * CACHE_SET_SIZE] as *mut u32).read_volatile();
-            report_api(a);
             let b = (&mut test_slice[set * CACHE_SET_SIZE + CACHE_SET_SIZE - 1] as *mut u32).read_volatile();
-            report_api(b);
+            Subsystem::Cache.report(Event::Value(event::cache::READ), &[a, b]);
         }
     }
     // flush cache
-    report_api(0xff00_ff00);
+    Subsystem::Cache.report(Event::Step(event::cache::FLUSH), &[]);
     core::arch::asm!(".word 0x500F",);
-    report_api(0x0f0f_0f0f);
+    Subsystem::Cache.report(Event::Step(event::cache::FLUSHED), &[]);
     // read cached values - first iteration populates the cache; second iteration should be cached
     for iter in 0..2 {
-        report_api(0xb2d0_0000 + iter + 1);
+        Subsystem::Cache.report(Event::Step(event::cache::POST_FLUSH + iter + 1), &[]);
         for set in 0..4 {
             let a = (&mut test_slice[set * CACHE_SET_SIZE] as *mut u32).read_volatile();
-            report_api(a);
             let b = (&mut test_slice[set * CACHE_SET_SIZE + CACHE_SET_SIZE - 1] as *mut u32).read_volatile();
-            report_api(b);
+            Subsystem::Cache.report(Event::Value(event::cache::READ), &[a, b]);
         }
     }
-    report_api(0x600c_ac7e);
+    Subsystem::Cache.report(Event::End, &[]);
 
     // check that caching is disabled for I/O regions
-    report_api(0xc520_0000);
+    Subsystem::Cache.report(Event::Step(event::cache::IO), &[]);
     let mut csrtest = CSR::new(utra::csrtest::HW_CSRTEST_BASE as *mut u32);
     let mut passing = 1;
     for i in 0..4 {
         csrtest.wfo(utra::csrtest::WTEST_WTEST, i);
         let val = csrtest.rf(utra::csrtest::RTEST_RTEST);
-        report_api(val);
+        Subsystem::Cache.report(Event::Value(event::cache::IO_READ), &[val]);
         if val != i + 0x1000_0000 {
             passing = 0;
         }
@@ -171,14 +180,11 @@ where
         // report_api(a);
     }
 
-    if sum == checksum {
-        report_api(checksum as u32);
-        report_api(0x600d_0000 + test_index);
+    if crate::check::diff_values("checksum", checksum, sum) {
+        Subsystem::Ram.report(Event::Pass(test_index as u16), &[checksum]);
         1
     } else {
-        report_api(checksum as u32);
-        report_api(sum as u32);
-        report_api(0x0bad_0000 + test_index);
+        Subsystem::Ram.report(Event::Fail(test_index as u16), &[]);
         0
     }
 }
@@ -208,14 +214,11 @@ where
         checksum += (d.as_ptr().add(1) as *const T).read_volatile().try_into().unwrap_or_default();
     }
 
-    if sum == checksum {
-        report_api(checksum as u32);
-        report_api(0x600d_0000 + test_index);
+    if crate::check::diff_values("checksum", checksum, sum) {
+        Subsystem::Ram.report(Event::Pass(test_index as u16), &[checksum]);
         1
     } else {
-        report_api(checksum as u32);
-        report_api(sum as u32);
-        report_api(0x0bad_0000 + test_index);
+        Subsystem::Ram.report(Event::Fail(test_index as u16), &[]);
         0
     }
 }
@@ -246,14 +249,11 @@ where
         // report_api(b);
     }
 
-    if sum == checksum {
-        report_api(checksum as u32);
-        report_api(0x600d_0000 + test_index);
+    if crate::check::diff_values("checksum", checksum, sum) {
+        Subsystem::Ram.report(Event::Pass(test_index as u16), &[checksum]);
         1
     } else {
-        report_api(checksum as u32);
-        report_api(sum as u32);
-        report_api(0x0bad_0000 + test_index);
+        Subsystem::Ram.report(Event::Fail(test_index as u16), &[]);
         0
     }
 }
@@ -266,7 +266,7 @@ where
     T: TryFrom<usize> + TryInto<u32> + Default + Copy,
 {
     if test_slice.len() != 512 {
-        report_api(0x0bad_000 + test_index + 0x0F00); // indicate a failure due to configuration
+        Subsystem::Ram.report(Event::Fail(event::cache::CONFIG + test_index as u16), &[]);
         return 0;
     }
     let mut state: u16 = 1;
@@ -281,9 +281,9 @@ where
     }
 
     // flush cache
-    report_api(0xff00_ff00);
+    Subsystem::Ram.report(Event::Step(event::cache::FLUSH), &[]);
     core::arch::asm!(".word 0x500F",);
-    report_api(0x0f0f_0f0f);
+    Subsystem::Ram.report(Event::Step(event::cache::FLUSHED), &[]);
 
     // we should be able to just iterate in-order and sum all the values, and get the same thing back as above
     let mut checksum: u32 = 0;
@@ -293,21 +293,18 @@ where
         // report_api(a);
     }
 
-    if sum == checksum {
-        report_api(checksum as u32);
-        report_api(0x600d_0000 + test_index);
+    if crate::check::diff_values("checksum", checksum, sum) {
+        Subsystem::Ram.report(Event::Pass(test_index as u16), &[checksum]);
         1
     } else {
-        report_api(checksum as u32);
-        report_api(sum as u32);
-        report_api(0x0bad_0000 + test_index);
+        Subsystem::Ram.report(Event::Fail(test_index as u16), &[]);
         0
     }
 }
 
 pub fn xip_test() -> usize {
     let mut passing = 0;
-    report_api(0x61D0_0000);
+    Subsystem::Xip.report(Event::Begin, &[]);
     // a code snippet that adds 0x400 to the argument and returns
     let code = [0x4005_0513u32, 0x0000_8082u32];
 
@@ -320,7 +317,7 @@ pub fn xip_test() -> usize {
     let mut expected: usize = test_val;
     for _ in 0..8 {
         test_val = crate::asm::jmp_remote(test_val, satp::XIP_VA);
-        report_api(test_val as u32);
+        Subsystem::Xip.report(Event::Value(event::xip::RESULT), &[test_val as u32]);
         expected += 0x0400;
         assert!(expected == test_val);
         passing += 1;
@@ -340,11 +337,11 @@ pub fn xip_test() -> usize {
     // run the new code and see that it was updated?
     for _ in 0..8 {
         test_val = crate::asm::jmp_remote(test_val, satp::XIP_VA + XIP_OFFSET);
-        report_api(test_val as u32);
+        Subsystem::Xip.report(Event::Value(event::xip::RESULT), &[test_val as u32]);
         expected += 1;
         assert!(expected == test_val);
         passing += 1;
     }
-    report_api(0x61D0_600D);
+    Subsystem::Xip.report(Event::End, &[]);
     passing
 }
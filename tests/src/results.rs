@@ -0,0 +1,116 @@
+// (c) Copyright CrossBar, Inc. 2024.
+//
+// This documentation describes Open Hardware and is licensed under the
+// [CERN-OHL-W-2.0].
+//
+// You may redistribute and modify this documentation under the terms of the
+// [CERN-OHL- W-2.0 (http://ohwr.org/cernohl)]. This documentation is
+// distributed WITHOUT ANY EXPRESS OR IMPLIED WARRANTY, INCLUDING OF
+// MERCHANTABILITY, SATISFACTORY QUALITY AND FITNESS FOR A PARTICULAR PURPOSE.
+// Please see the [CERN-OHL- W-2.0] for applicable conditions.
+
+//! Machine-readable record of a test run, for CI and testbenches.
+//!
+//! The record is emitted two ways at the end of the run:
+//!   - as `@@`-prefixed, `|`-separated lines on the console, and
+//!   - as a binary mailbox in SRAM at `RESULTS`, which a testbench can dump.
+//!
+//! `cargo xtask results` parses either form. Keep the two in sync with `xtask/src/results.rs`.
+//!
+//! Console form:
+//! ```text
+//! @@RESULT|1|<suite count>
+//! @@SUITE|<id>|<name>|<outcome>|<total>|<passed>|<skipped>|<expected failures>|<failed case ids>|<elapsed ticks>|<cycles>
+//! @@END|<failed suite count>
+//! ```
+//! The outcome is one of the `SuiteOutcome::tag()` strings (`pass`, `fail`, `skip`, `xfail`, `xpass`,
+//! `notrun`). Failed case IDs are `<index>:<name>` pairs separated by `,`; the index is the order in which
+//! the suite recorded the case.
+//!
+//! Mailbox form, all little-endian `u32`: a header of `[RESULTS_MAGIC, RESULTS_VERSION, count,
+//! RECORD_WORDS]` followed by `count` records of `RECORD_WORDS` words each:
+//! `[id, outcome, total, passed, skipped, expected failures, failed count, failed case mask,
+//! elapsed lo, elapsed hi, cycles lo, cycles hi, name (16 bytes, NUL padded)]`, where the outcome is
+//! `SuiteOutcome::code()`. The magic is written last, so a valid magic means a complete
+//! record.
+
+use crate::harness;
+use crate::*;
+
+pub const RESULTS: usize = harness::HARNESS_BASE + 0x1000;
+/// Size of the mailbox region documented in `link.x`
+pub const RESULTS_LEN: usize = 0x800;
+pub const RESULTS_MAGIC: u32 = 0x7e57_2e5d;
+pub const RESULTS_VERSION: u32 = 1;
+pub const HEADER_WORDS: usize = 4;
+pub const RECORD_WORDS: usize = 16;
+const NAME_BYTES: usize = 16;
+/// Number of records that fit in the mailbox. This is one short of `harness::MAX_SUITES`, but
+/// there are fewer `SuiteId`s than that.
+const MAX_RECORDS: usize = (RESULTS_LEN / 4 - HEADER_WORDS) / RECORD_WORDS;
+
+/// Invalidates any record left over from a previous run.
+pub fn clear() { unsafe { (RESULTS as *mut u32).write_volatile(0) } }
+
+fn put(word: usize, value: u32) { unsafe { (RESULTS as *mut u32).add(word).write_volatile(value) } }
+
+/// Suites that were enabled, or that are declared as skipped, appear in the record.
+fn reported(test: &dyn Test) -> bool { test.is_enabled() || test.outcome() != harness::SuiteOutcome::NotRun }
+
+/// Emits the result record for every reported suite.
+pub fn emit(tests: &[&mut dyn Test]) {
+    let count = tests.iter().filter(|t| reported(&***t)).count().min(MAX_RECORDS);
+    println!("@@RESULT|{}|{}", RESULTS_VERSION, count);
+    put(1, RESULTS_VERSION);
+    put(2, count as u32);
+    put(3, RECORD_WORDS as u32);
+
+    let mut failed_suites = 0;
+    for (n, test) in tests.iter().filter(|t| reported(&***t)).take(MAX_RECORDS).enumerate() {
+        let cases = test.cases();
+        let outcome = test.outcome();
+        if outcome.is_failure() {
+            failed_suites += 1;
+        }
+
+        print!(
+            "@@SUITE|{}|{}|{}|{}|{}|{}|{}|",
+            test.id() as u32,
+            test.name(),
+            outcome.tag(),
+            test.total_tests(),
+            test.passing_tests(),
+            cases.skipped(),
+            cases.failed_as_expected()
+        );
+        let mut failed_mask = 0u32;
+        for (i, (id, case)) in cases.failure_ids().enumerate() {
+            print!("{}{}:{}", if i == 0 { "" } else { "," }, id, case.name);
+            failed_mask |= 1 << id;
+        }
+        println!("|{}|{}", test.stats().elapsed_ticks(), test.stats().cycles);
+
+        let base = HEADER_WORDS + n * RECORD_WORDS;
+        put(base, test.id() as u32);
+        put(base + 1, outcome.code());
+        put(base + 2, test.total_tests() as u32);
+        put(base + 3, test.passing_tests() as u32);
+        put(base + 4, cases.skipped() as u32);
+        put(base + 5, cases.failed_as_expected() as u32);
+        put(base + 6, cases.failed() as u32);
+        put(base + 7, failed_mask);
+        put(base + 8, test.stats().elapsed_ticks() as u32);
+        put(base + 9, (test.stats().elapsed_ticks() >> 32) as u32);
+        put(base + 10, test.stats().cycles as u32);
+        put(base + 11, (test.stats().cycles >> 32) as u32);
+        let mut name = [0u8; NAME_BYTES];
+        for (d, &s) in name.iter_mut().zip(test.name().as_bytes()) {
+            *d = s;
+        }
+        for (i, chunk) in name.chunks(4).enumerate() {
+            put(base + 12 + i, u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]));
+        }
+    }
+    println!("@@END|{}", failed_suites);
+    put(0, RESULTS_MAGIC);
+}
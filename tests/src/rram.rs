@@ -12,6 +12,7 @@
 use utralib::generated::*;
 use xous_pl230::*;
 
+use crate::event::{self, Event, Subsystem};
 use crate::utils::*;
 use crate::*;
 
@@ -88,38 +89,43 @@ const LIFECYCLE_TESTS: usize =
     4 * 4 * 2 + 1; // acram test cases: 4 modes * 4 combos of rw bits * (read + write test) + final check
 
 const TOTAL_TESTS: usize = QUICK_TESTS + CORNERS_TOTAL;
-crate::impl_test!(RramTests, "RRAM", TOTAL_TESTS);
+crate::impl_test!(RramTests, "RRAM", TOTAL_TESTS, Rram);
+
+/// Watchdog budget of the suites that write RRAM, which is slow to program
+const RRAM_BUDGET_TICKS: u32 = 30_000;
 
 impl TestRunner for RramTests {
     fn run(&mut self) {
-        self.passing_tests += rram_tests_early();
-        self.passing_tests += rram_tests_corners(false);
+        self.cases.tally("quick", rram_tests_early(), QUICK_TESTS);
+        rram_tests_corners(&mut self.cases, false);
     }
+
+    fn budget_ticks(&self) -> u32 { RRAM_BUDGET_TICKS }
 }
 
-crate::impl_test!(RramDisturbTests, "RRAM Disturb", CORNERS_TOTAL);
+crate::impl_test!(RramDisturbTests, "RRAM Disturb", CORNERS_TOTAL, RramDisturb, requires = [Rram]);
 /// A test runner just for verifying that RRAM was not disturbed.
 impl TestRunner for RramDisturbTests {
-    fn run(&mut self) { self.passing_tests += rram_tests_corners(true); }
+    fn run(&mut self) { rram_tests_corners(&mut self.cases, true); }
 }
 
-pub fn rram_tests_corners(verify_only: bool) -> usize {
+pub fn rram_tests_corners(cases: &mut crate::harness::CaseLog, verify_only: bool) -> usize {
     let mut reram = Reram::new();
     let mut test = [0u32; CORNER_TESTS];
     let mut seed = 0xe692_b0f6;
     let mut passing = 0;
     let byte_offsets = [
         // aligned mid-write
-        0x20_0000,
+        ("corner_mid", 0x20_0000),
         // end cap
-        0x40_0000 - CORNER_TESTS * size_of::<u32>(),
+        ("corner_end", 0x40_0000 - CORNER_TESTS * size_of::<u32>()),
         // beginning
-        0x0,
+        ("corner_begin", 0x0),
         // unaligned
-        0x12_3455,
+        ("corner_unaligned", 0x12_3455),
     ];
 
-    for offset in byte_offsets {
+    for (name, offset) in byte_offsets {
         for d in test.iter_mut() {
             seed = crate::lfsr_next_u32(seed);
             *d = seed;
@@ -138,13 +144,13 @@ pub fn rram_tests_corners(verify_only: bool) -> usize {
                 test.len() * size_of::<u32>(),
             )
         };
-        for (i, (&s, &d)) in rram_check.iter().zip(data.iter()).enumerate() {
-            if s == d {
-                passing += 1;
-            } else {
-                crate::println!("Err: s {:x} -> d {:x} @ {:x}", s, d, offset + utralib::HW_RERAM_MEM + i);
-            }
+        let mut diff = crate::check::Diff::new(name);
+        for (i, (s, &d)) in rram_check.iter().zip(data.iter()).enumerate() {
+            diff.word(i, Some(s as *const u8 as usize), *s, d);
         }
+        let corner_passing = data.len() - diff.mismatches();
+        diff.finish();
+        passing += cases.tally(name, corner_passing, data.len());
     }
 
     crate::println!(
@@ -193,32 +199,36 @@ pub fn rram_tests_early() -> usize {
     {
         let rslice = &reram.read_slice()[byte_offset / core::mem::size_of::<u32>()
             ..byte_offset / core::mem::size_of::<u32>() + rbk.len()];
-        for (i, (&s, &d)) in test_data.iter().zip(rslice.iter()).enumerate() {
-            if s != d {
-                crate::println!("@{}: w {:x} -> r {:x}", i, s, d);
-            } else {
-                passing += 1;
-            }
+        let mut diff = crate::check::Diff::new("base");
+        for (i, (&s, d)) in test_data.iter().zip(rslice.iter()).enumerate() {
+            diff.word(i, Some(d as *const u32 as usize), *d, s);
         }
+        passing += QUICK_TESTS - diff.mismatches();
+        diff.finish();
     }
     crate::println!("Base: passing {} of {}", passing, QUICK_TESTS);
     passing
 }
 
+fn report_readback(addr: *const u32) {
+    let value = unsafe { addr.read_volatile() };
+    Subsystem::Rram.report(Event::Value(event::rram::READBACK), &[addr as u32, value]);
+}
+
 pub fn rram_tests_late() {
     let mut uart = crate::debug::Uart {};
     let rram_base = 0x6000_0000 as *const u32;
     uart.tiny_write_str("0x6000_0000:\r");
     for i in 0..8 {
-        report_api(unsafe { rram_base.add(i).read_volatile() });
+        report_readback(unsafe { rram_base.add(i) });
     }
     uart.tiny_write_str("0x6020_0000:\r");
     for i in 0..8 {
-        report_api(unsafe { rram_base.add(i + 0x20_0000 / core::mem::size_of::<u32>()).read_volatile() });
+        report_readback(unsafe { rram_base.add(i + 0x20_0000 / core::mem::size_of::<u32>()) });
     }
     uart.tiny_write_str("0x603f8000:\r");
     for i in 0..8 {
-        report_api(unsafe { rram_base.add(i + 0x3F_8000 / core::mem::size_of::<u32>()).read_volatile() });
+        report_readback(unsafe { rram_base.add(i + 0x3F_8000 / core::mem::size_of::<u32>()) });
     }
     /*
     let mut reram = Reram::new();
@@ -252,11 +262,11 @@ pub fn rram_tests_late() {
 
     uart.tiny_write_str("0x6020_0000:\r");
     for i in 0..8 {
-        report_api(unsafe { rram_base.add(i + 0x20_0000 / core::mem::size_of::<u32>()).read_volatile() });
+        report_readback(unsafe { rram_base.add(i + 0x20_0000 / core::mem::size_of::<u32>()) });
     }
     uart.tiny_write_str("0x6000_0000:\r");
     for i in 0..8 {
-        report_api(unsafe { rram_base.add(i).read_volatile() });
+        report_readback(unsafe { rram_base.add(i) });
     }
 }
 
@@ -312,18 +322,16 @@ impl Reram {
     pub unsafe fn write_u32_aligned(&mut self, addr: usize, data: &[u32]) {
         assert!(addr % 0x20 == 0, "unaligned destination address!");
         assert!(data.len() % 8 == 0, "unaligned source data!");
-        // crate::print!("@ {:x} > ", addr);
         for (outer, d) in data.chunks_exact(8).enumerate() {
+            crate::trace!("@ {:x} > {:x?}", addr + outer * 32, d);
             // write the data to the buffer
             for (inner, &datum) in d.iter().enumerate() {
-                // crate::print!(" {:x}", datum);
                 self.array
                     .as_mut_ptr()
                     .add(addr / core::mem::size_of::<u32>() + outer * 8 + inner)
                     .write_volatile(datum);
                 core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
             }
-            // crate::println!("");
 
             self.csr.rmwf(utra::rrc::SFR_RRCCR_SFR_RRCCR, rrc::RRC_CR_WRITE_CMD | SECURITY_MODE);
             self.array
@@ -433,8 +441,8 @@ impl Reram {
 
         let mut timeout = 0;
         while (DmaChanControl(cc_struct.channels[0].control).cycle_ctrl() != 0) && timeout < 16 {
-            // report_api("dma progress ", cc_struct.channels[0].control);
-            report_api(unsafe { cc_struct.channels.as_ptr().read() }.control);
+            let control = unsafe { cc_struct.channels.as_ptr().read() }.control;
+            Subsystem::Rram.report(Event::Value(event::rram::DMA_CONTROL), &[control]);
             timeout += 1;
         }
 
@@ -474,9 +482,11 @@ pub const ONEWAY_START: usize = 0x603D_A000;
 pub const ONEWAY2_START: usize = 0x603D_B000;
 pub const CODESEL_END: usize = 0x603D_A000;
 
-crate::impl_test!(RramLifecycle, "RRAM Lifecycle", LIFECYCLE_TESTS);
+crate::impl_test!(RramLifecycle, "RRAM Lifecycle", LIFECYCLE_TESTS, RramLifecycle);
 impl TestRunner for RramLifecycle {
-    fn run(&mut self) { self.passing_tests += rram_lockzones(); }
+    fn run(&mut self) { self.cases.tally("lockzones", rram_lockzones(), LIFECYCLE_TESTS); }
+
+    fn budget_ticks(&self) -> u32 { RRAM_BUDGET_TICKS }
 }
 
 /*
@@ -539,12 +549,7 @@ fn case_region(offset: usize) -> AccessRegion {
     }
 }
 fn acram_encode(readable: bool, writeable: bool, onehot_user_id: CoreuserId, prog_only: bool) -> u32 {
-    let mut ret = 0;
-    ret |= if !readable { 1 } else { 0 };
-    ret |= if !writeable { 2 } else { 0 };
-    ret |= (onehot_user_id as u32) << 20;
-    ret |= if prog_only { 1 << 24 } else { 0 };
-    ret
+    tests::acram::encode(readable, writeable, onehot_user_id as u32, prog_only)
 }
 
 fn data_default(offset: usize) -> u32 {
@@ -859,7 +864,7 @@ fn acram_update_case(base: usize, reram: &mut Reram, coreuser: &mut CSR<u32>) ->
             if writeable && (onehot_coreuser == init_coreuser) {
                 array_data.copy_from_slice(&new_data);
                 final_check_data.copy_from_slice(&new_data);
-                // crate::println!("copied final check {:x?}", final_check_data);
+                crate::trace!("copied final check {:x?}", final_check_data);
             }
             if readable {
                 check_data.copy_from_slice(&secure_slice);
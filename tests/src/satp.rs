@@ -9,6 +9,7 @@
 // MERCHANTABILITY, SATISFACTORY QUALITY AND FITNESS FOR A PARTICULAR PURPOSE.
 // Please see the [CERN-OHL- W-2.0] for applicable conditions.
 
+use tests::sv32::{FLG_R, FLG_U, FLG_W, FLG_X, PAGE_SIZE, PageTable, WORD_SIZE, set_l1_pte, set_l2_pte};
 #[cfg(feature = "coreuser-onehot")]
 use utra::coreuser::*;
 #[cfg(feature = "coreuser-lutop")]
@@ -19,46 +20,27 @@ use utra::coreuser::{
 #[cfg(feature = "coreuser-compression")]
 use utralib::generated::*;
 
+use crate::event::{Event, Subsystem};
 use crate::utra::coreuser::{STATUS, STATUS_MM};
 use crate::*;
 
 const SATP_TESTS: usize = 1;
-crate::impl_test!(SatpTests, "SATP", SATP_TESTS);
+crate::impl_test!(SatpTests, "SATP", SATP_TESTS, Satp, requires = [SatpSetup, IrqSetup]);
 impl TestRunner for SatpTests {
     fn run(&mut self) {
-        // This relies on both SATP and IRQs being setup
-        self.passing_tests += satp_test();
+        self.cases.tally("satp_test", satp_test(), SATP_TESTS);
     }
 }
 const SATP_SETUP: usize = 1;
-crate::impl_test!(SatpSetup, "SATP Setup", SATP_SETUP);
+crate::impl_test!(SatpSetup, "SATP Setup", SATP_SETUP, SatpSetup);
 impl TestRunner for SatpSetup {
     fn run(&mut self) {
         satp_setup();
         print!("vmem enabled\r");
-        self.passing_tests += 1;
+        self.cases.pass("satp_setup");
     }
 }
 
-pub const PAGE_SIZE: usize = 4096;
-const WORD_SIZE: usize = core::mem::size_of::<u32>();
-
-const FLG_VALID: usize = 0x1;
-const FLG_X: usize = 0x8;
-const FLG_W: usize = 0x4;
-const FLG_R: usize = 0x2;
-#[allow(dead_code)]
-const FLG_U: usize = 0x10;
-#[allow(dead_code)]
-const FLG_A: usize = 0x40;
-#[allow(dead_code)]
-const FLG_D: usize = 0x80;
-
-#[repr(C)]
-pub struct PageTable {
-    entries: [usize; PAGE_SIZE / WORD_SIZE],
-}
-
 // locate the page table entries
 const ROOT_PT_PA: usize = 0x6100_0000; // 1st level at base of sram
 // 2nd level PTs
@@ -87,19 +69,6 @@ const RV_VA: usize = 0xE000_0000;
 // PAs (when different from VAs)
 const RERAM_PA: usize = 0x6000_0000;
 
-fn set_l1_pte(from_va: usize, to_pa: usize, root_pt: &mut PageTable) {
-    let index = from_va >> 22;
-    root_pt.entries[index] = ((to_pa & 0xFFFF_FC00) >> 2) // top 2 bits of PA are not used, we don't do 34-bit PA featured by Sv32
-        | FLG_VALID;
-}
-
-fn set_l2_pte(from_va: usize, to_pa: usize, l2_pt: &mut PageTable, flags: usize) {
-    let index = (from_va >> 12) & 0x3_FF;
-    l2_pt.entries[index] = ((to_pa & 0xFFFF_FC00) >> 2) // top 2 bits of PA are not used, we don't do 34-bit PA featured by Sv32
-        | flags
-        | FLG_VALID;
-}
-
 /// Very simple Sv32 setup that drops into supervisor (kernel) mode, with most
 /// mappings being 1:1 between VA->PA, except for code which is remapped to address 0x0 in VA space.
 #[inline(never)] // correct behavior depends on RA being set.
@@ -192,6 +161,8 @@ pub fn satp_setup() {
             "li          t0, 0xffffffff",
             "csrw        mideleg, t0",
             "csrw        medeleg, t0",
+            // Let supervisor mode read the cycle counter, for suite timing
+            "csrw        mcounteren, t0",
 
             // Return to Supervisor mode (1 << 11) when we call `reti`.
             // Disable interrupts (0 << 5), allow supervisor mode to run user mode code (1 << 18)
@@ -233,7 +204,7 @@ pub fn to_user_mode() {
 
 pub fn satp_test() -> usize {
     let mut passing = 1;
-    report_api(0x5a1d_0000);
+    Subsystem::Satp.report(Event::Begin, &[]);
     #[cfg(feature = "coreuser-compression")]
     {
         let mut coreuser = CSR::new(utra::coreuser::HW_COREUSER_BASE as *mut u32);
@@ -256,13 +227,14 @@ pub fn satp_test() -> usize {
             );
         }
         // readback of table
-        /* // this is too slow with the Daric UART model, but uncomment it later when running on real hardware
-        for asid in 0..512 {
-            coreuser.wfo(utra::coreuser::GET_ASID_ADDR_ASID, asid);
-            report_api(
-                coreuser.rf(utra::coreuser::GET_ASID_VALUE_VALUE) << 16 | asid
-            );
-        } */
+        // this is too slow with the Daric UART model, so it is only done on request (e.g. `satp=debug`)
+        if crate::log_enabled!(Debug) {
+            for asid in 0..512 {
+                coreuser.wfo(utra::coreuser::GET_ASID_ADDR_ASID, asid);
+                let entry = coreuser.rf(utra::coreuser::GET_ASID_VALUE_VALUE);
+                Subsystem::Satp.report(Event::Value(crate::event::satp::ASID), &[asid, entry]);
+            }
+        }
 
         // setup window on our root page. Narrowly define it to *just* one page.
         coreuser.wfo(utra::coreuser::WINDOW_AH_PPN, (ROOT_PT_PA >> 12) as u32);
@@ -292,7 +264,8 @@ pub fn satp_test() -> usize {
         // partial readback of table; `2` should not be trusted
         for asid in 0..4 {
             coreuser.wfo(utra::coreuser::GET_ASID_ADDR_ASID, asid);
-            report_api(coreuser.rf(utra::coreuser::GET_ASID_VALUE_VALUE) << 16 | asid);
+            let entry = coreuser.rf(utra::coreuser::GET_ASID_VALUE_VALUE);
+            Subsystem::Satp.report(Event::Value(crate::event::satp::ASID), &[asid, entry]);
         }
     }
     #[cfg(feature = "coreuser-onehot")]
@@ -633,7 +606,7 @@ pub fn satp_test() -> usize {
     // now try changing the SATP around and see that the coreuser value updates
     // since we are in supervisor mode we can diddle with this at will, normally
     // user processes can't change this
-    report_api(0x5a1d_0001);
+    Subsystem::Satp.report(Event::Step(1), &[]);
     for asid in 0..512 {
         let satp: u32 = 0x8000_0000 | asid << 22 | (ROOT_PT_PA as u32 >> 12);
         unsafe {
@@ -663,13 +636,13 @@ pub fn satp_test() -> usize {
     }
 
     // switch to user mode
-    report_api(0x5a1d_0002);
+    Subsystem::Satp.report(Event::Step(2), &[]);
     to_user_mode();
 
     // attempt to change ASID. This should be ignored or cause a trap, depending on the config of the device!
     // confirmed that without interrupts configured this has no effect; although it causes the following three
     // instructions to be ignored on the error.
-    report_api(0x5a1d_0003);
+    Subsystem::Satp.report(Event::Step(3), &[]);
     let satp: u32 = 0x8000_0000 | 4 << 22 | (ROOT_PT_PA as u32 >> 12);
     unsafe {
         core::arch::asm!(
@@ -684,7 +657,7 @@ pub fn satp_test() -> usize {
             satp_val = in(reg) satp,
         );
     }
-    report_api(0x5a1d_0004);
+    Subsystem::Satp.report(Event::Step(4), &[]);
 
     // confirm that the MM signal has changed polarity
     if coreuser.rf(STATUS_MM) != 0 {
@@ -693,7 +666,7 @@ pub fn satp_test() -> usize {
         passing = 0;
     }
 
-    report_api(0x5a1d_600d);
+    Subsystem::Satp.report(Event::End, &[]);
     passing
 }
 
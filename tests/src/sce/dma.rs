@@ -9,6 +9,8 @@
 // MERCHANTABILITY, SATISFACTORY QUALITY AND FITNESS FOR A PARTICULAR PURPOSE.
 // Please see the [CERN-OHL- W-2.0] for applicable conditions.
 
+use core::mem::{size_of, size_of_val};
+
 use utralib::generated::*;
 
 use crate::debug;
@@ -16,9 +18,9 @@ use crate::utils::*;
 use crate::{TestBoilerplate, TestRunner};
 
 const SCE_TESTS: usize = 2;
-crate::impl_test!(SceDmaTests, "SCE DMA", SCE_TESTS);
+crate::impl_test!(SceDmaTests, "SCE DMA", SCE_TESTS, SceDma, requires = [SatpSetup, IrqSetup]);
 impl TestRunner for SceDmaTests {
-    fn run(&mut self) { self.passing_tests += sce_dma_tests(); }
+    fn run(&mut self) { self.cases.tally("sce_dma", sce_dma_tests(), SCE_TESTS); }
 }
 
 pub fn sce_dma_tests() -> usize {
@@ -105,26 +107,15 @@ pub fn sce_dma_tests() -> usize {
         )
     };
     sce_ctl_csr.wfo(utra::sce_glbsfr::SFR_APBS_CR_APBSOPT, 0b1_0000); // endian swap APB read
-    uart.tiny_write_str("HOUT (BE): ");
-    for i in 0..8 {
-        // should be big-endian
-        uart.print_hex_word(hout_mem[i]);
-    }
-    uart.tiny_write_str("\r");
+    uart.tiny_write_str("HOUT (BE):\r");
+    // should be big-endian
+    debug::hexdump(hout_mem.as_ptr() as usize, 8 * size_of::<u32>());
     sce_ctl_csr.wfo(utra::sce_glbsfr::SFR_APBS_CR_APBSOPT, 0b0_0000);
-    uart.tiny_write_str("HOUT (LE): ");
-    for i in 0..8 {
-        // should be big-endian
-        uart.print_hex_word(hout_mem[i]);
-    }
-    uart.tiny_write_str("\r");
+    uart.tiny_write_str("HOUT (LE):\r");
+    debug::hexdump(hout_mem.as_ptr() as usize, 8 * size_of::<u32>());
 
-    uart.tiny_write_str("HIN ");
-    for d in region_a {
-        // big-endian, so make it one big string
-        uart.print_hex_word(d);
-    }
-    uart.tiny_write_str("\r");
+    uart.tiny_write_str("HIN:\r");
+    debug::hexdump(region_a.as_ptr() as usize, size_of_val(&region_a));
 
     // -------- AES tests ---------
     // fifo 2 = AES in, fifo 3 = AES out -- this must happen first
@@ -245,20 +236,9 @@ pub fn sce_dma_tests() -> usize {
         core::arch::asm!(".word 0x500F", "nop", "nop", "nop", "nop", "nop",);
     }
 
-    errs = 0;
     // compare a to c: these should now be identical, with enc->dec
-    for (i, (src, dst)) in region_a.iter().zip(region_c.iter()).enumerate() {
-        if *src != *dst {
-            uart.tiny_write_str("error in iter ");
-            uart.print_hex_word(i as u32);
-            uart.tiny_write_str(": ");
-            uart.print_hex_word(*src);
-            uart.tiny_write_str(" s<->d ");
-            uart.print_hex_word(*dst);
-            uart.tiny_write_str("\r");
-            errs += 1;
-        }
-    }
+    errs =
+        debug::memdiff(region_a.as_ptr() as usize, region_c.as_ptr() as usize, size_of_val(&region_a)) as u32;
     uart.tiny_write_str("errs: ");
     uart.print_hex_word(errs);
     uart.tiny_write_str("\r");
@@ -0,0 +1,156 @@
+// (c) Copyright CrossBar, Inc. 2024.
+//
+// This documentation describes Open Hardware and is licensed under the
+// [CERN-OHL-W-2.0].
+//
+// You may redistribute and modify this documentation under the terms of the
+// [CERN-OHL- W-2.0 (http://ohwr.org/cernohl)]. This documentation is
+// distributed WITHOUT ANY EXPRESS OR IMPLIED WARRANTY, INCLUDING OF
+// MERCHANTABILITY, SATISFACTORY QUALITY AND FITNESS FOR A PARTICULAR PURPOSE.
+// Please see the [CERN-OHL- W-2.0] for applicable conditions.
+
+//! Soak mode, for counting intermittent failures during silicon bring-up.
+//!
+//! After the normal run, the suites in `SelectTable::soak_mask` are repeated until
+//! `soak_iterations` iterations have run, `soak_ticks` have elapsed, or (with
+//! `FLAG_SOAK_STOP_ON_FAIL`) an iteration fails. Only suites that were selected for the normal
+//! run and whose prerequisites passed are soaked, so setup stages are not repeated.
+
+use crate::harness::{self, Exit, MAX_SUITES};
+use crate::*;
+
+/// Interval between progress reports, in ticktimer ticks
+const PROGRESS_TICKS: u64 = 10_000;
+
+/// Aggregate results for one soaked suite
+#[derive(Copy, Clone)]
+struct SoakStats {
+    runs: u32,
+    clean: u32,
+    first_failure: Option<u32>,
+    /// Passing checks, summed over all iterations
+    checks: u64,
+    min_checks: usize,
+}
+
+impl SoakStats {
+    const EMPTY: SoakStats =
+        SoakStats { runs: 0, clean: 0, first_failure: None, checks: 0, min_checks: usize::MAX };
+}
+
+/// Repeats the soak suites, if soak mode was requested. `passed` is the mask of suites that passed
+/// in the normal run.
+pub fn soak(tests: &mut [&mut dyn Test], passed: u32) {
+    let table = match harness::select_table() {
+        Some(table) if table.soak_mask != 0 => table,
+        _ => return,
+    };
+    let (order, count) = harness::schedule(tests);
+    let mut soaked = [false; MAX_SUITES];
+    for &i in order[..count].iter() {
+        soaked[i] = (table.soak_mask & tests[i].id().mask()) != 0
+            && harness::blocking_prerequisite(tests, tests[i].requires(), passed).is_none();
+    }
+
+    println!(
+        "Soak: mask {:08x}, {} iterations, {} tick limit",
+        table.soak_mask, table.soak_iterations, table.soak_ticks
+    );
+    let mut stats = [SoakStats::EMPTY; MAX_SUITES];
+    let mut iteration = 0u32;
+    let mut failing_iterations = 0u32;
+    let start = harness::now_ticks();
+    let mut last_progress = start;
+    loop {
+        if table.soak_iterations != 0 && iteration >= table.soak_iterations {
+            break;
+        }
+        if table.soak_ticks != 0 && harness::now_ticks().wrapping_sub(start) >= table.soak_ticks as u64 {
+            println!("Soak: time limit reached");
+            break;
+        }
+        iteration += 1;
+
+        let mut failed = false;
+        let mut timed_out = false;
+        for &i in order[..count].iter().filter(|&&i| soaked[i]) {
+            let test = &mut tests[i];
+            test.cases_mut().clear();
+            timed_out = harness::run_timed(&mut **test, passed) == Exit::Timeout;
+            let s = &mut stats[i];
+            s.runs += 1;
+            s.checks += test.passing_tests() as u64;
+            s.min_checks = s.min_checks.min(test.passing_tests());
+            if test.outcome().is_failure() {
+                failed = true;
+                if s.first_failure.is_none() {
+                    println!("Soak: {} first failed in iteration {}", test.name(), iteration);
+                    s.first_failure = Some(iteration);
+                }
+            } else {
+                s.clean += 1;
+            }
+            if timed_out && (table.flags & harness::FLAG_STOP_ON_TIMEOUT) != 0 {
+                break;
+            }
+        }
+        if failed {
+            failing_iterations += 1;
+        }
+        if timed_out && (table.flags & harness::FLAG_STOP_ON_TIMEOUT) != 0 {
+            println!("Soak: stopping after a timeout");
+            break;
+        }
+        if failed && (table.flags & harness::FLAG_SOAK_STOP_ON_FAIL) != 0 {
+            println!("Soak: stopping at the first failure");
+            break;
+        }
+
+        let now = harness::now_ticks();
+        if now.wrapping_sub(last_progress) >= PROGRESS_TICKS {
+            println!(
+                "Soak: iteration {} at {} ticks, {} failing iterations",
+                iteration,
+                now.wrapping_sub(start),
+                failing_iterations
+            );
+            last_progress = now;
+        }
+    }
+
+    println!(
+        "Soak done: {} iterations in {} ticks, {} failing",
+        iteration,
+        harness::now_ticks().wrapping_sub(start),
+        failing_iterations
+    );
+    for (i, test) in tests.iter_mut().enumerate().filter(|(i, _)| soaked[*i]) {
+        let s = &stats[i];
+        if s.runs == 0 {
+            continue;
+        }
+        match s.first_failure {
+            Some(first) => println!(
+                "Soak {}: {}/{} iterations clean, first failure in iteration {}, min {}/{} checks, {} total",
+                test.name(),
+                s.clean,
+                s.runs,
+                first,
+                s.min_checks,
+                test.total_tests(),
+                s.checks
+            ),
+            None => println!(
+                "Soak {}: {}/{} iterations clean, {} checks total",
+                test.name(),
+                s.clean,
+                s.runs,
+                s.checks
+            ),
+        }
+        // the case log only holds the last iteration; don't let it hide an earlier failure
+        if s.first_failure.is_some() && !test.outcome().is_failure() {
+            test.cases_mut().fail("soak", "failed in an earlier soak iteration");
+        }
+    }
+}
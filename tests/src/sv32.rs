@@ -0,0 +1,127 @@
+// (c) Copyright CrossBar, Inc. 2024.
+//
+// This documentation describes Open Hardware and is licensed under the
+// [CERN-OHL-W-2.0].
+//
+// You may redistribute and modify this documentation under the terms of the
+// [CERN-OHL- W-2.0 (http://ohwr.org/cernohl)]. This documentation is
+// distributed WITHOUT ANY EXPRESS OR IMPLIED WARRANTY, INCLUDING OF
+// MERCHANTABILITY, SATISFACTORY QUALITY AND FITNESS FOR A PARTICULAR PURPOSE.
+// Please see the [CERN-OHL- W-2.0] for applicable conditions.
+
+//! Sv32 page table entries, as set up by `satp::satp_setup()`.
+
+pub const PAGE_SIZE: usize = 4096;
+pub const WORD_SIZE: usize = core::mem::size_of::<u32>();
+
+pub const FLG_VALID: usize = 0x1;
+pub const FLG_X: usize = 0x8;
+pub const FLG_W: usize = 0x4;
+pub const FLG_R: usize = 0x2;
+pub const FLG_U: usize = 0x10;
+pub const FLG_A: usize = 0x40;
+pub const FLG_D: usize = 0x80;
+
+/// One page of PTEs. Entries are 32 bits wide on every target, so that the layout is the same
+/// when the helpers are unit-tested on the host.
+#[repr(C)]
+pub struct PageTable {
+    entries: [u32; PAGE_SIZE / WORD_SIZE],
+}
+const _: () = assert!(core::mem::size_of::<PageTable>() == PAGE_SIZE);
+
+/// Points the root PTE for the 4MiB superpage containing `from_va` at the 2nd level table at
+/// `to_pa`.
+pub fn set_l1_pte(from_va: usize, to_pa: usize, root_pt: &mut PageTable) {
+    let index = from_va >> 22;
+    root_pt.entries[index] = (((to_pa & 0xFFFF_FC00) >> 2) // top 2 bits of PA are not used, we don't do 34-bit PA featured by Sv32
+        | FLG_VALID) as u32;
+}
+
+/// Maps the page containing `from_va` to `to_pa` with `flags`.
+pub fn set_l2_pte(from_va: usize, to_pa: usize, l2_pt: &mut PageTable, flags: usize) {
+    let index = (from_va >> 12) & 0x3_FF;
+    l2_pt.entries[index] = (((to_pa & 0xFFFF_FC00) >> 2) // top 2 bits of PA are not used, we don't do 34-bit PA featured by Sv32
+        | flags
+        | FLG_VALID) as u32;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty() -> PageTable { PageTable { entries: [0; PAGE_SIZE / WORD_SIZE] } }
+
+    /// Physical address an entry points to
+    fn pte_pa(pte: u32) -> usize { ((pte as usize) >> 10) << 12 }
+
+    /// Walks the two levels the way the MMU would, with `l2` standing in for the table the root
+    /// entry points to.
+    fn translate(root: &PageTable, l2: &PageTable, l2_pa: usize, va: usize) -> Option<(usize, usize)> {
+        let l1 = root.entries[va >> 22];
+        // a leaf has any of R/W/X set; the firmware only uses pointers at the root
+        if l1 as usize & FLG_VALID == 0 || l1 as usize & (FLG_R | FLG_W | FLG_X) != 0 {
+            return None;
+        }
+        assert_eq!(pte_pa(l1), l2_pa, "walk reached a different table");
+        let l2 = l2.entries[(va >> 12) & 0x3ff];
+        if l2 as usize & FLG_VALID == 0 {
+            return None;
+        }
+        Some((pte_pa(l2) | (va & (PAGE_SIZE - 1)), l2 as usize & 0x3ff))
+    }
+
+    #[test]
+    fn l1_pointer_entry() {
+        let mut root = empty();
+        set_l1_pte(0x6000_0000, 0x6100_2000, &mut root);
+        assert_eq!(root.entries[0x180], 0x1840_0801);
+        assert_eq!(root.entries.iter().filter(|&&e| e != 0).count(), 1);
+    }
+
+    #[test]
+    fn l2_leaf_entry() {
+        let mut pt = empty();
+        set_l2_pte(0x6000_1000, 0x6000_1000, &mut pt, FLG_X | FLG_R | FLG_U);
+        assert_eq!(pt.entries[1], 0x1800_041b);
+    }
+
+    #[test]
+    fn l2_index_ignores_superpage() {
+        let mut a = empty();
+        let mut b = empty();
+        set_l2_pte(0x4000_3000, 0x4000_3000, &mut a, FLG_R);
+        set_l2_pte(0x5800_3000, 0x4000_3000, &mut b, FLG_R);
+        assert_eq!(a.entries[3], b.entries[3]);
+    }
+
+    #[test]
+    fn remapped_code_translates() {
+        // code is the one region where VA and PA differ in `satp_setup()`
+        const CODE_VA: usize = 0x6000_0000;
+        const RERAM_PA: usize = 0x6000_0000;
+        const CODE_PT_PA: usize = 0x6100_2000;
+        let mut root = empty();
+        let mut code_pt = empty();
+        set_l1_pte(CODE_VA, CODE_PT_PA, &mut root);
+        for offset in (0..0x40_0000).step_by(PAGE_SIZE) {
+            set_l2_pte(CODE_VA + offset, RERAM_PA + offset, &mut code_pt, FLG_X | FLG_R | FLG_U | FLG_W);
+        }
+        for &va in &[CODE_VA, CODE_VA + 0x1234, CODE_VA + 0x3f_fffc] {
+            let (pa, flags) = translate(&root, &code_pt, CODE_PT_PA, va).unwrap();
+            assert_eq!(pa, va - CODE_VA + RERAM_PA);
+            assert_eq!(flags, FLG_X | FLG_R | FLG_U | FLG_W | FLG_VALID);
+        }
+        assert!(translate(&root, &code_pt, CODE_PT_PA, 0x5000_0000).is_none());
+    }
+
+    #[test]
+    fn unmapped_page_faults() {
+        let mut root = empty();
+        let mut pt = empty();
+        set_l1_pte(0x6100_0000, 0x6100_1000, &mut root);
+        set_l2_pte(0x6100_0000, 0x6100_0000, &mut pt, FLG_R);
+        assert!(translate(&root, &pt, 0x6100_1000, 0x6100_0010).is_some());
+        assert!(translate(&root, &pt, 0x6100_1000, 0x6100_1010).is_none());
+    }
+}
@@ -14,9 +14,9 @@ use utralib::generated::*;
 use crate::*;
 
 const TIMER0_TESTS: usize = 2;
-crate::impl_test!(Timer0Tests, "Timer0", TIMER0_TESTS);
+crate::impl_test!(Timer0Tests, "Timer0", TIMER0_TESTS, Timer0, requires = [IrqSetup]);
 impl TestRunner for Timer0Tests {
-    /// Note: this implementation is dependent upon the `irq` module!
+    /// Note: this implementation is dependent upon the `irq` module's trap handler reloading timer0
     fn run(&mut self) {
         let mut timer0 = CSR::new(utra::timer0::HW_TIMER0_BASE as *mut u32);
 
@@ -31,9 +31,7 @@ impl TestRunner for Timer0Tests {
         let value_a = timer0.r(utra::timer0::VALUE);
         timer0.wo(utra::timer0::UPDATE_VALUE, 1); // latch another value
         let value_b = timer0.r(utra::timer0::VALUE);
-        if value_b < value_a {
-            self.passing_tests += 1;
-        } else {
+        if !self.cases.check("decrement", value_b < value_a, "timer did not decrement") {
             crate::println!("TIMER0 did not decrement: {}->{}", value_b, value_a);
         }
 
@@ -52,13 +50,13 @@ impl TestRunner for Timer0Tests {
             if timer0.r(utra::timer0::RELOAD) == 10_000 {
                 // the enable will be de-activated by the interrupt handler
                 crate::println!("TIMER0 interrupt caught");
-                self.passing_tests += 1;
+                self.cases.pass("irq");
                 break;
             }
             timeout += 1;
             if timeout >= 1000 {
-                // timeout without incrementing passing_tests
                 crate::println!("TIMER0 timed out");
+                self.cases.fail("irq", "timed out waiting for interrupt");
                 break;
             }
         }
@@ -66,4 +64,6 @@ impl TestRunner for Timer0Tests {
         timer0.wfo(utra::timer0::EV_PENDING_ZERO, 0);
         timer0.wfo(utra::timer0::EV_ENABLE_ZERO, 0);
     }
+
+    fn buffered_log(&self) -> bool { true }
 }
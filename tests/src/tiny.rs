@@ -0,0 +1,135 @@
+// (c) Copyright CrossBar, Inc. 2024.
+//
+// This documentation describes Open Hardware and is licensed under the
+// [CERN-OHL-W-2.0].
+//
+// You may redistribute and modify this documentation under the terms of the
+// [CERN-OHL- W-2.0 (http://ohwr.org/cernohl)]. This documentation is
+// distributed WITHOUT ANY EXPRESS OR IMPLIED WARRANTY, INCLUDING OF
+// MERCHANTABILITY, SATISFACTORY QUALITY AND FITNESS FOR A PARTICULAR PURPOSE.
+// Please see the [CERN-OHL- W-2.0] for applicable conditions.
+
+//! Console output without `core::fmt`, for builds where code size matters, such as `bio-quick`.
+//!
+//! `tiny_print!` and `tiny_println!` take a comma-separated list of items instead of a format
+//! string: string slices and integers (printed in decimal) as they are, and `hex()`, `hex_w()`,
+//! `dec_w()` or `pad()` for other layouts:
+//!
+//! ```ignore
+//! tiny_println!("[", tiny::hex_w(addr, 8), "] ", tiny::pad(name, 12), tiny::dec_w(count, 5));
+//! ```
+//!
+//! Output goes straight to `debug::Uart`, like `tiny_write_str()`, so it is never buffered.
+
+#![allow(dead_code)] // the layouts are mostly used by feature-gated code
+
+use crate::debug::Uart;
+
+/// Something `tiny_print!` can print.
+pub trait TinyDisplay {
+    fn tiny_fmt(&self, uart: &mut Uart);
+}
+
+/// Prints `value` in `radix`, right-aligned to `width` characters with `fill`.
+fn put_unsigned(uart: &mut Uart, value: u32, radix: u32, width: usize, fill: u8, negative: bool) {
+    // wide enough for any `u32` in decimal or hex, plus a sign; `width` is clamped to it
+    let mut buf = [fill; 16];
+    let mut i = buf.len();
+    let mut v = value;
+    loop {
+        let d = (v % radix) as u8;
+        i -= 1;
+        buf[i] = if d < 10 { b'0' + d } else { b'a' + d - 10 };
+        v /= radix;
+        if v == 0 {
+            break;
+        }
+    }
+    if negative {
+        i -= 1;
+        buf[i] = b'-';
+    }
+    let start = i.min(buf.len() - width.min(buf.len()));
+    uart.put_bytes(&buf[start..]);
+}
+
+impl TinyDisplay for str {
+    fn tiny_fmt(&self, uart: &mut Uart) { uart.tiny_write_str(self); }
+}
+
+impl<T: TinyDisplay + ?Sized> TinyDisplay for &T {
+    fn tiny_fmt(&self, uart: &mut Uart) { (**self).tiny_fmt(uart); }
+}
+
+macro_rules! impl_unsigned {
+    ($($t:ty),*) => {
+        $(impl TinyDisplay for $t {
+            fn tiny_fmt(&self, uart: &mut Uart) { put_unsigned(uart, *self as u32, 10, 0, b' ', false); }
+        })*
+    };
+}
+impl_unsigned!(u8, u16, u32, usize);
+
+impl TinyDisplay for i32 {
+    fn tiny_fmt(&self, uart: &mut Uart) { put_unsigned(uart, self.unsigned_abs(), 10, 0, b' ', *self < 0); }
+}
+
+impl TinyDisplay for bool {
+    fn tiny_fmt(&self, uart: &mut Uart) { uart.tiny_write_str(if *self { "true" } else { "false" }); }
+}
+
+/// A number in a given radix and width; see `hex()`, `hex_w()` and `dec_w()`.
+pub struct Num {
+    value: u32,
+    radix: u32,
+    width: usize,
+    fill: u8,
+}
+
+impl TinyDisplay for Num {
+    fn tiny_fmt(&self, uart: &mut Uart) {
+        put_unsigned(uart, self.value, self.radix, self.width, self.fill, false);
+    }
+}
+
+/// `value` in lowercase hex, with no leading zeros.
+pub fn hex(value: u32) -> Num { Num { value, radix: 16, width: 0, fill: b'0' } }
+
+/// `value` in lowercase hex, zero-padded to `width` digits.
+pub fn hex_w(value: u32, width: usize) -> Num { Num { value, radix: 16, width, fill: b'0' } }
+
+/// `value` in decimal, right-aligned to `width` characters.
+pub fn dec_w(value: u32, width: usize) -> Num { Num { value, radix: 10, width, fill: b' ' } }
+
+/// A string left-aligned in a field; see `pad()`.
+pub struct Pad<'a> {
+    s: &'a str,
+    width: usize,
+}
+
+impl TinyDisplay for Pad<'_> {
+    fn tiny_fmt(&self, uart: &mut Uart) {
+        uart.tiny_write_str(self.s);
+        for _ in self.s.len()..self.width {
+            uart.put_bytes(b" ");
+        }
+    }
+}
+
+/// `s` left-aligned and padded with spaces to `width` characters.
+pub fn pad(s: &str, width: usize) -> Pad<'_> { Pad { s, width } }
+
+#[macro_export]
+macro_rules! tiny_print {
+    ($($item:expr),* $(,)?) => {{
+        let mut uart = $crate::debug::Uart {};
+        $($crate::tiny::TinyDisplay::tiny_fmt(&$item, &mut uart);)*
+    }};
+}
+
+#[macro_export]
+macro_rules! tiny_println {
+    ($($item:expr),* $(,)?) => {{
+        $crate::tiny_print!($($item,)* "\r");
+    }};
+}
@@ -0,0 +1,108 @@
+// (c) Copyright CrossBar, Inc. 2024.
+//
+// This documentation describes Open Hardware and is licensed under the
+// [CERN-OHL-W-2.0].
+//
+// You may redistribute and modify this documentation under the terms of the
+// [CERN-OHL- W-2.0 (http://ohwr.org/cernohl)]. This documentation is
+// distributed WITHOUT ANY EXPRESS OR IMPLIED WARRANTY, INCLUDING OF
+// MERCHANTABILITY, SATISFACTORY QUALITY AND FITNESS FOR A PARTICULAR PURPOSE.
+// Please see the [CERN-OHL- W-2.0] for applicable conditions.
+
+//! Mirrors of the console output that survive a misconfigured DUART.
+//!
+//! Everything sent to the console, through `print!` or the `debug::Uart` helpers, is also written
+//! to a circular trace buffer in SRAM, from which `cargo xtask tracebuf` recovers it out of a
+//! post-mortem memory dump. If `FLAG_LOG_CSRTEST` is set in the selection table, it is also
+//! written to `csrtest` as `console::TEXT` events, four bytes at a time, so that it shows up in
+//! simulation traces.
+//!
+//! The buffer is set up first thing in `rust_entry`, so it also catches the clock setup.
+
+use utralib::generated::*;
+
+use crate::event::{self, Event, Subsystem};
+use crate::harness;
+
+/// Buffer state; see `link.x`. `xtask/src/tracebuf.rs` decodes it.
+pub const TRACE_STATE: usize = 0x6110_4000;
+/// Buffer data
+pub const TRACE_DATA: usize = 0x6110_5000;
+/// Buffer size in bytes; must be a power of two
+pub const TRACE_LEN: usize = 0x2000;
+
+const TRACE_MAGIC: u32 = 0x7ace_b0f0;
+const TRACE_VERSION: u32 = 1;
+
+#[repr(C)]
+struct TraceState {
+    /// `TRACE_MAGIC` once the buffer is set up
+    magic: u32,
+    version: u32,
+    /// `TRACE_LEN`, for the host-side decoder
+    len: u32,
+    /// Free-running count of bytes written; the oldest byte is lost once it exceeds `len`
+    head: u32,
+    /// Bytes waiting to be reported to `csrtest`, packed little-endian
+    pending: u32,
+    /// Number of bytes in `pending`
+    pending_len: u32,
+}
+
+fn state() -> *mut TraceState { TRACE_STATE as *mut TraceState }
+
+/// Empties the buffer and marks it valid.
+pub fn init() {
+    unsafe {
+        (*state()).version = TRACE_VERSION;
+        (*state()).len = TRACE_LEN as u32;
+        (*state()).head = 0;
+        (*state()).pending = 0;
+        (*state()).pending_len = 0;
+        core::ptr::addr_of_mut!((*state()).magic).write_volatile(TRACE_MAGIC);
+    }
+}
+
+fn is_valid() -> bool { unsafe { core::ptr::addr_of!((*state()).magic).read_volatile() == TRACE_MAGIC } }
+
+/// Appends `s` to the trace buffer, and to `csrtest` if `FLAG_LOG_CSRTEST` is set. Does nothing
+/// before `init()`.
+pub fn mirror(s: &[u8]) {
+    if !is_valid() {
+        return;
+    }
+    let to_csrtest = (harness::select_flags() & harness::FLAG_LOG_CSRTEST) != 0;
+    for &c in s {
+        unsafe {
+            let head = core::ptr::addr_of!((*state()).head).read_volatile();
+            (TRACE_DATA as *mut u8).add(head as usize & (TRACE_LEN - 1)).write_volatile(c);
+            core::ptr::addr_of_mut!((*state()).head).write_volatile(head.wrapping_add(1));
+        }
+        if to_csrtest {
+            report(c);
+        }
+    }
+}
+
+/// Packs `c` into the pending `csrtest` word, reporting the word once it is full or at the end of
+/// a line. The event is written directly rather than through `report_api`, so that console text
+/// doesn't replace the last report code shown for a suite that times out.
+fn report(c: u8) {
+    unsafe {
+        let len = (*state()).pending_len;
+        let word = (*state()).pending | (c as u32) << (len * 8);
+        if len == 3 || c == b'\r' || c == b'\n' {
+            (*state()).pending = 0;
+            (*state()).pending_len = 0;
+            let mut csrtest = CSR::new(utra::csrtest::HW_CSRTEST_BASE as *mut u32);
+            csrtest.wo(
+                utra::csrtest::WTEST,
+                event::tag(Subsystem::Console, Event::Value(event::console::TEXT), 1),
+            );
+            csrtest.wo(utra::csrtest::WTEST, word);
+        } else {
+            (*state()).pending = word;
+            (*state()).pending_len = len + 1;
+        }
+    }
+}
@@ -12,7 +12,7 @@ use crate::*;
 // 2 for spim (one with reset in the middle)
 // 1 for i2c
 const UDMA_TESTS: usize = 2 + 1;
-crate::impl_test!(UdmaTests, "UDMA", UDMA_TESTS);
+crate::impl_test!(UdmaTests, "UDMA", UDMA_TESTS, Udma);
 impl TestRunner for UdmaTests {
     fn run(&mut self) {
         // To check this result:
@@ -31,7 +31,8 @@ impl TestRunner for UdmaTests {
         iox.set_ports_from_pio_bitmask(0x00_7f_e7ff);
         println!("piosel {:x}", iox.csr.r(utra::iox::SFR_PIOSEL));
 
-        self.passing_tests += self.i2c_test();
+        let i2c_passing = self.i2c_test();
+        self.cases.tally("i2c", i2c_passing, 1);
 
         let channel = cramium_hal::board::setup_memory_pins(&iox);
         udma_global.clock_on(PeriphId::from(channel));
@@ -82,27 +83,22 @@ impl TestRunner for UdmaTests {
             crate::println!("initiate read");
             if flash_spim.mem_read(0x1000 + (test_iter as u32 + 1) * 16, &mut dest, false) {
                 crate::println!("rom_read done!");
-                for chunk in dest[..32].chunks(4) {
-                    crate::println!("{:x}", u32::from_le_bytes(chunk.try_into().unwrap()));
-                }
-                let mut passing = true;
+                crate::debug::hexdump(dest.as_ptr() as usize, 32);
+                let mut diff = crate::check::Diff::new("spim_read");
                 for (i, chunk) in dest.chunks(4).enumerate() {
                     let checkval = u32::from_le_bytes(chunk.try_into().unwrap());
                     let expected =
                         0xface_8000 + i as u32 + (test_iter as u32 + 1) * 16 / size_of::<u32>() as u32;
-                    if checkval != expected {
-                        crate::println!("fail {}, expected {:x} got {:x}", i, expected, checkval);
-                        passing = false;
-                    }
+                    diff.word(i, Some(chunk.as_ptr() as usize), checkval, expected);
                 }
-                if passing {
+                if self.cases.check("spim_read", diff.finish(), "readback did not match flash contents") {
                     crate::println!("rom_read check passed!");
-                    self.passing_tests += 1;
                 } else {
                     crate::println!("rom_read check FAILED!!!!");
                 }
             } else {
                 crate::println!("rom_read failed");
+                self.cases.fail("spim_read", "mem_read failed");
             }
 
             // perform a reset
@@ -115,6 +111,9 @@ impl TestRunner for UdmaTests {
         iox.set_ports_from_pio_bitmask(0xFFFF_FFFF);
         println!("piosel {:x}", iox.csr.r(utra::iox::SFR_PIOSEL));
     }
+
+    /// the SPIM stress transfers and the I2C polling take longer than the default budget
+    fn budget_ticks(&self) -> u32 { 20_000 }
 }
 
 pub const I2C_IFRAM_ADDR: usize = utralib::HW_IFRAM0_MEM + utralib::HW_IFRAM0_MEM_LEN - 8 * 4096;
@@ -155,11 +154,9 @@ impl UdmaTests {
                     crate::println!("rbk length mismatch {} != {}", len, data.len());
                     passing = false;
                 }
-                for (i, (&w, &r)) in data.iter().zip(check.iter()).enumerate() {
-                    if w != r {
-                        crate::println!("{:x}: w{:x} -> r{:x}", i as u8 + adr, w, r);
-                        passing = false;
-                    }
+                // index 0 of the readback is EEPROM address `adr`
+                if !crate::check::diff_slices("i2c readback", &check, &data) {
+                    passing = false;
                 }
             }
             Ok(u) => {
@@ -202,11 +199,9 @@ impl UdmaTests {
                     crate::println!("rbk length mismatch {} != {}", len, data.len());
                     passing = false;
                 }
-                for (i, (&w, &r)) in data.iter().zip(check.iter()).enumerate() {
-                    if w != r {
-                        crate::println!("{:x}: w{:x} -> r{:x}", i as u8 + adr, w, r);
-                        passing = false;
-                    }
+                // index 0 of the readback is EEPROM address `adr`
+                if !crate::check::diff_slices("i2c readback", &check, &data) {
+                    passing = false;
                 }
             }
             Ok(u) => {
@@ -0,0 +1,290 @@
+//! Just enough of an ELF reader for the firmware images: the loadable segments, sections and
+//! symbols of a 32-bit little-endian executable.
+
+use crate::DynError;
+
+const PT_LOAD: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_NOBITS: u32 = 8;
+const SHF_ALLOC: u32 = 2;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+
+/// The file contents of a loadable segment, which go into memory at `paddr`.
+pub struct Segment {
+    /// Load (physical) address
+    pub paddr: u64,
+    /// Run (virtual) address
+    pub vaddr: u64,
+    /// Size in memory, including any zero-filled tail past `data`
+    pub memsz: u64,
+    pub data: Vec<u8>,
+}
+
+/// A section that takes up memory when the image runs
+pub struct Section {
+    pub name: String,
+    pub addr: u64,
+    pub size: u64,
+    /// Zero-filled at startup rather than loaded, like `.bss`
+    pub nobits: bool,
+}
+
+/// A function or data object with a size
+pub struct Symbol {
+    /// As in the symbol table, so Rust names are still mangled
+    pub name: String,
+    pub addr: u64,
+    pub size: u64,
+}
+
+pub struct Elf {
+    pub entry: u64,
+    pub segments: Vec<Segment>,
+    pub sections: Vec<Section>,
+    /// Empty if the symbol table was stripped
+    pub symbols: Vec<Symbol>,
+}
+
+/// Lays `segments` out as one image, from the lowest load address to the highest, with the gaps
+/// between them filled with zeros. Returns the load address of the image too, or `None` if there
+/// is nothing to load.
+pub fn flatten<'a>(segments: impl Iterator<Item = &'a Segment> + Clone) -> Option<(u64, Vec<u8>)> {
+    let segments = segments.filter(|s| !s.data.is_empty());
+    let start = segments.clone().map(|s| s.paddr).min()?;
+    let end = segments.clone().map(|s| s.paddr + s.data.len() as u64).max()?;
+    let mut image = vec![0u8; (end - start) as usize];
+    for s in segments {
+        let offset = (s.paddr - start) as usize;
+        image[offset..offset + s.data.len()].copy_from_slice(&s.data);
+    }
+    Some((start, image))
+}
+
+impl Elf {
+    /// The whole loadable contents, as `objcopy -O binary` would write them.
+    pub fn flat_image(&self) -> Option<(u64, Vec<u8>)> { flatten(self.segments.iter()) }
+
+    /// Where the contents of `section` are loaded from, which differs from where it runs for
+    /// initialised data. `None` for sections that aren't loaded.
+    pub fn load_addr(&self, section: &Section) -> Option<u64> {
+        if section.nobits {
+            return None;
+        }
+        self.segments
+            .iter()
+            .find(|s| section.addr >= s.vaddr && section.addr < s.vaddr + s.memsz)
+            .map(|s| s.paddr + (section.addr - s.vaddr))
+    }
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Result<u16, DynError> {
+    let b = data.get(offset..offset + 2).ok_or("truncated ELF file")?;
+    Ok(u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Result<u32, DynError> {
+    let b = data.get(offset..offset + 4).ok_or("truncated ELF file")?;
+    Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// The NUL-terminated string at `offset`
+fn str_at(data: &[u8], offset: usize) -> Result<String, DynError> {
+    let b = data.get(offset..).ok_or("truncated ELF file")?;
+    let len = b.iter().position(|&c| c == 0).ok_or("unterminated string in ELF file")?;
+    Ok(String::from_utf8_lossy(&b[..len]).into_owned())
+}
+
+pub fn parse(data: &[u8]) -> Result<Elf, DynError> {
+    if data.get(..4) != Some(b"\x7fELF") {
+        return Err("not an ELF file".into());
+    }
+    // EI_CLASS and EI_DATA
+    if data.get(4..6) != Some(&[1, 1]) {
+        return Err("not a 32-bit little-endian ELF file".into());
+    }
+    let entry = u32_at(data, 0x18)? as u64;
+    let phoff = u32_at(data, 0x1c)? as usize;
+    let phentsize = u16_at(data, 0x2a)? as usize;
+    let phnum = u16_at(data, 0x2c)? as usize;
+
+    let mut segments = Vec::new();
+    for i in 0..phnum {
+        let ph = phoff + i * phentsize;
+        if u32_at(data, ph)? != PT_LOAD {
+            continue;
+        }
+        let offset = u32_at(data, ph + 4)? as usize;
+        let filesz = u32_at(data, ph + 16)? as usize;
+        let contents =
+            data.get(offset..offset + filesz).ok_or("ELF segment extends past the end of the file")?;
+        segments.push(Segment {
+            paddr: u32_at(data, ph + 12)? as u64,
+            vaddr: u32_at(data, ph + 8)? as u64,
+            memsz: u32_at(data, ph + 20)? as u64,
+            data: contents.to_vec(),
+        });
+    }
+
+    let shoff = u32_at(data, 0x20)? as usize;
+    let shentsize = u16_at(data, 0x2e)? as usize;
+    let shnum = u16_at(data, 0x30)? as usize;
+    let shstrndx = u16_at(data, 0x32)? as usize;
+    // (type, flags, addr, offset, size, link) of each section header
+    let mut headers = Vec::new();
+    for i in 0..shnum {
+        let sh = shoff + i * shentsize;
+        headers.push((
+            u32_at(data, sh + 4)?,
+            u32_at(data, sh + 8)?,
+            u32_at(data, sh + 12)? as u64,
+            u32_at(data, sh + 16)? as usize,
+            u32_at(data, sh + 20)? as usize,
+            u32_at(data, sh + 24)? as usize,
+        ));
+    }
+    let shstrtab = headers.get(shstrndx).map(|h| h.3).unwrap_or(0);
+
+    let mut sections = Vec::new();
+    let mut symbols = Vec::new();
+    for (i, &(kind, flags, addr, offset, size, link)) in headers.iter().enumerate() {
+        if flags & SHF_ALLOC != 0 && size != 0 {
+            sections.push(Section {
+                name: str_at(data, shstrtab + u32_at(data, shoff + i * shentsize)? as usize)?,
+                addr,
+                size: size as u64,
+                nobits: kind == SHT_NOBITS,
+            });
+        }
+        if kind == SHT_SYMTAB {
+            let strtab = headers.get(link).ok_or("bad string table index in ELF file")?.3;
+            // 16-byte entries; the first is the null symbol
+            for sym in (offset..offset + size).step_by(16).skip(1) {
+                let info = *data.get(sym + 12).ok_or("truncated ELF file")?;
+                let size = u32_at(data, sym + 8)? as u64;
+                if !matches!(info & 0xf, STT_OBJECT | STT_FUNC) || size == 0 {
+                    continue;
+                }
+                symbols.push(Symbol {
+                    name: str_at(data, strtab + u32_at(data, sym)? as usize)?,
+                    addr: u32_at(data, sym + 4)? as u64,
+                    size,
+                });
+            }
+        }
+    }
+    Ok(Elf { entry, segments, sections, symbols })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn put16(f: &mut [u8], offset: usize, v: u16) { f[offset..offset + 2].copy_from_slice(&v.to_le_bytes()); }
+
+    fn put32(f: &mut [u8], offset: usize, v: u32) { f[offset..offset + 4].copy_from_slice(&v.to_le_bytes()); }
+
+    /// An executable with `.text` in RRAM, `.data` loaded after it but run from RAM, `.bss` in RAM
+    /// with nothing loaded, and a segment that isn't PT_LOAD.
+    fn fixture() -> Vec<u8> {
+        const PHOFF: usize = 52;
+        const TEXT: usize = PHOFF + 4 * 32;
+        const DATA: usize = TEXT + 10;
+        const SHSTRTAB: usize = DATA + 6;
+        const NAMES: &[u8] = b"\0.text\0.data\0.bss\0.shstrtab\0";
+        const SHOFF: usize = SHSTRTAB + NAMES.len();
+        let mut f = vec![0u8; SHOFF + 5 * 40];
+        f[..6].copy_from_slice(b"\x7fELF\x01\x01");
+        put32(&mut f, 0x18, 0x6000_0000);
+        put32(&mut f, 0x1c, PHOFF as u32);
+        put16(&mut f, 0x2a, 32);
+        put16(&mut f, 0x2c, 4);
+        put32(&mut f, 0x20, SHOFF as u32);
+        put16(&mut f, 0x2e, 40);
+        put16(&mut f, 0x30, 5);
+        put16(&mut f, 0x32, 4);
+
+        // (type, offset, vaddr, paddr, filesz, memsz)
+        let phdrs = [
+            (PT_LOAD, TEXT, 0x6000_0000, 0x6000_0000, 10, 10),
+            (PT_LOAD, DATA, 0x6100_a000, 0x6000_0020, 6, 8),
+            (PT_LOAD, 0, 0x6100_a100, 0x6100_a100, 0, 0x100),
+            // PT_NOTE
+            (4, TEXT, 0, 0, 4, 4),
+        ];
+        for (i, &(kind, offset, vaddr, paddr, filesz, memsz)) in phdrs.iter().enumerate() {
+            let ph = PHOFF + i * 32;
+            for (j, v) in [kind, offset as u32, vaddr, paddr, filesz, memsz].iter().enumerate() {
+                put32(&mut f, ph + j * 4, *v);
+            }
+        }
+        // `.text` then `.data` contents, numbered from 1
+        for i in 0..16 {
+            f[TEXT + i] = i as u8 + 1;
+        }
+        f[SHSTRTAB..SHOFF].copy_from_slice(NAMES);
+
+        // (name, type, flags, addr, offset, size); the first is the null section
+        let shdrs = [
+            (0, 0, 0, 0, 0, 0),
+            (1, 1, SHF_ALLOC, 0x6000_0000, TEXT, 10),
+            (7, 1, SHF_ALLOC, 0x6100_a000, DATA, 6),
+            (13, SHT_NOBITS, SHF_ALLOC, 0x6100_a100, 0, 0x100),
+            (18, 3, 0, 0, SHSTRTAB, NAMES.len()),
+        ];
+        for (i, &(name, kind, flags, addr, offset, size)) in shdrs.iter().enumerate() {
+            let sh = SHOFF + i * 40;
+            for (j, v) in [name, kind, flags, addr, offset as u32, size as u32].iter().enumerate() {
+                put32(&mut f, sh + j * 4, *v);
+            }
+        }
+        f
+    }
+
+    #[test]
+    fn segments_and_sections() {
+        let elf = parse(&fixture()).unwrap();
+        assert_eq!(elf.entry, 0x6000_0000);
+        let segments: Vec<(u64, u64, u64, usize)> =
+            elf.segments.iter().map(|s| (s.paddr, s.vaddr, s.memsz, s.data.len())).collect();
+        assert_eq!(
+            segments,
+            [
+                (0x6000_0000, 0x6000_0000, 10, 10),
+                (0x6000_0020, 0x6100_a000, 8, 6),
+                (0x6100_a100, 0x6100_a100, 0x100, 0)
+            ]
+        );
+        assert_eq!(elf.segments[1].data, [11, 12, 13, 14, 15, 16]);
+
+        let names: Vec<&str> = elf.sections.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, [".text", ".data", ".bss"]);
+        let load: Vec<Option<u64>> = elf.sections.iter().map(|s| elf.load_addr(s)).collect();
+        assert_eq!(load, [Some(0x6000_0000), Some(0x6000_0020), None]);
+        assert!(elf.symbols.is_empty());
+    }
+
+    #[test]
+    fn flat_image() {
+        let elf = parse(&fixture()).unwrap();
+        // `.bss` loads nothing, so the image ends with `.data` at its load address
+        let (start, image) = elf.flat_image().unwrap();
+        assert_eq!(start, 0x6000_0000);
+        assert_eq!(image.len(), 0x26);
+        assert_eq!(image[..10], (1..=10).collect::<Vec<u8>>()[..]);
+        assert!(image[10..0x20].iter().all(|&b| b == 0));
+        assert_eq!(image[0x20..], [11, 12, 13, 14, 15, 16]);
+
+        let bin = crate::hw_bin(&elf).unwrap();
+        assert_eq!(bin.len(), 0x40);
+        assert_eq!(bin[..0x26], image[..]);
+        assert!(bin[0x26..].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn truncated() {
+        let f = fixture();
+        assert!(parse(&f[..100]).is_err());
+        assert!(parse(b"\x7fELF\x02\x01").is_err());
+    }
+}
@@ -0,0 +1,106 @@
+//! Feature selection for the firmware build: comma-separated `--feature` lists, the named presets
+//! in `xtask/presets.toml`, and a check against the features `tests/Cargo.toml` declares.
+
+use crate::{DynError, project_root};
+
+/// Reads the `key = value` entries of `[section]` in a TOML file, joining values that span
+/// several lines. Only handles what the files read here use: one entry per key, no quoted keys,
+/// and `#` only in comments.
+fn section_entries(toml: &str, section: &str) -> Vec<(String, String)> {
+    let header = format!("[{}]", section);
+    let mut entries: Vec<(String, String)> = Vec::new();
+    let mut in_section = false;
+    // brackets and braces still open in the value being read
+    let mut depth = 0i32;
+    let nesting = |s: &str| s.matches(['[', '{']).count() as i32 - s.matches([']', '}']).count() as i32;
+    for line in toml.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if depth > 0 {
+            if let Some((_, value)) = entries.last_mut() {
+                value.push(' ');
+                value.push_str(line);
+            }
+            depth += nesting(line);
+            continue;
+        }
+        if line.starts_with('[') {
+            in_section = line == header;
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            let value = value.trim();
+            depth = nesting(value);
+            entries.push((key.trim().to_string(), value.to_string()));
+        }
+    }
+    entries
+}
+
+/// The strings in a TOML array of strings
+fn parse_array(value: &str) -> Vec<String> {
+    value
+        .trim()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .split(',')
+        .map(|s| s.trim().trim_matches('"').to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Features of a crate: its `[features]`, plus the implicit ones of its optional dependencies
+fn known_features(cargo_toml: &str) -> Vec<String> {
+    let mut known: Vec<String> =
+        section_entries(cargo_toml, "features").into_iter().map(|(k, _)| k).collect();
+    known.extend(
+        section_entries(cargo_toml, "dependencies")
+            .into_iter()
+            .filter(|(_, v)| v.replace(' ', "").contains("optional=true"))
+            .map(|(k, _)| k),
+    );
+    known
+}
+
+/// Expands `--preset` names and `--feature` lists into the features to build with, in order and
+/// without duplicates. Warns about features that `tests/Cargo.toml` doesn't declare.
+pub fn resolve(features: &[String], presets: &[String]) -> Result<Vec<String>, DynError> {
+    let mut list: Vec<String> = Vec::new();
+    if !presets.is_empty() {
+        let path = project_root().join("xtask").join("presets.toml");
+        let toml = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let defined = section_entries(&toml, "presets");
+        for name in presets {
+            let (_, value) = defined.iter().find(|(k, _)| k == name).ok_or_else(|| {
+                let names: Vec<&str> = defined.iter().map(|(k, _)| k.as_str()).collect();
+                format!("unknown preset `{}`; {} defines {}", name, path.display(), names.join(", "))
+            })?;
+            list.extend(parse_array(value));
+        }
+    }
+    list.extend(features.iter().flat_map(|f| f.split(',')).map(|f| f.trim().to_string()));
+    let mut resolved: Vec<String> = Vec::new();
+    for feature in list {
+        if !feature.is_empty() && !resolved.contains(&feature) {
+            resolved.push(feature);
+        }
+    }
+
+    let cargo_toml = std::fs::read_to_string(project_root().join("tests").join("Cargo.toml"))?;
+    let known = known_features(&cargo_toml);
+    // `dep/feature` names a feature of a dependency, which cargo checks
+    for feature in resolved.iter().filter(|f| !f.contains('/') && !known.contains(f)) {
+        let suggestion = format!("{}-tests", feature);
+        if known.contains(&suggestion) {
+            println!(
+                "warning: tests/Cargo.toml has no feature `{}`; did you mean `{}`?",
+                feature, suggestion
+            );
+        } else {
+            println!("warning: tests/Cargo.toml has no feature `{}`", feature);
+        }
+    }
+    Ok(resolved)
+}
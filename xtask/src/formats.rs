@@ -0,0 +1,252 @@
+//! Extra `boot-image` outputs for RTL simulations and RRAM programmers, converted here rather than
+//! with `objcopy`:
+//!
+//! - `readmemh`: `rv32.mem`, the binary as Verilog `$readmemh` hex, one word per line. `--word-bits` sets the
+//!   word width; 256 matches RRAM's write buffer.
+//! - `ihex`: `rv32.hex`, the loadable contents of the ELF as Intel HEX, at their load addresses.
+//! - `split`: `rv32-<region>.bin`, one raw image per memory region of `tests/link.x` that has anything loaded
+//!   into it, starting at the lowest address loaded.
+
+use std::path::Path;
+
+use crate::{DynError, RRAM_BASE, RRAM_LEN, elf};
+
+/// Memory regions of `tests/link.x`, as `(name, start, length)`
+const MEMORY: [(&str, u64, u64); 2] =
+    [("flash", RRAM_BASE, RRAM_LEN), ("ram", 0x6100_A000, 0x20_0000 - 0xA000)];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// The flat binary, which is always written
+    Bin,
+    /// `$readmemh` hex with words of the given number of bits
+    Readmemh(usize),
+    Ihex,
+    Split,
+}
+
+/// Checks the `--format` and `--word-bits` arguments before anything is built. Formats may be
+/// given as separate flags or as a comma-separated list.
+pub fn parse(formats: &[String], word_bits: &[String]) -> Result<Vec<Format>, DynError> {
+    let bits = match word_bits.last() {
+        Some(bits) => match crate::parse_u64(bits)? {
+            bits @ (32 | 64 | 128 | 256) => bits as usize,
+            bits => return Err(format!("--word-bits must be 32, 64, 128 or 256, not {}", bits).into()),
+        },
+        None => 32,
+    };
+    let mut list = Vec::new();
+    for name in formats.iter().flat_map(|f| f.split(',')).filter(|f| !f.is_empty()) {
+        list.push(match name {
+            "bin" => Format::Bin,
+            "readmemh" => Format::Readmemh(bits),
+            "ihex" => Format::Ihex,
+            "split" => Format::Split,
+            _ => {
+                return Err(format!("unknown format `{}`; expected bin, readmemh, ihex or split", name).into())
+            }
+        });
+    }
+    Ok(list)
+}
+
+/// Formats `image` as `$readmemh` input. Words are little-endian, so the first byte of each word is
+/// in its least significant bits; a short last word is padded with zeros.
+pub fn readmemh(image: &[u8], word_bits: usize) -> String {
+    let word_bytes = word_bits / 8;
+    let mut out = format!("// {} bytes from {:#x}, {}-bit words\n", image.len(), RRAM_BASE, word_bits);
+    for chunk in image.chunks(word_bytes) {
+        for i in (0..word_bytes).rev() {
+            out.push_str(&format!("{:02x}", chunk.get(i).copied().unwrap_or(0)));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// One Intel HEX record
+fn ihex_record(out: &mut String, kind: u8, address: u16, data: &[u8]) {
+    let mut bytes = vec![data.len() as u8, (address >> 8) as u8, address as u8, kind];
+    bytes.extend_from_slice(data);
+    let checksum = bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)).wrapping_neg();
+    out.push(':');
+    for b in bytes.iter().chain(std::iter::once(&checksum)) {
+        out.push_str(&format!("{:02X}", b));
+    }
+    out.push('\n');
+}
+
+/// Formats `regions` as Intel HEX, with 16-byte data records and the entry point as the start
+/// address.
+pub fn ihex(regions: &[Region], entry: u64) -> String {
+    let mut out = String::new();
+    let mut upper = None;
+    for region in regions {
+        let mut address = region.start;
+        let mut data = &region.data[..];
+        while !data.is_empty() {
+            if upper != Some(address >> 16) {
+                upper = Some(address >> 16);
+                ihex_record(&mut out, 4, 0, &((address >> 16) as u16).to_be_bytes());
+            }
+            // records don't cross a 64 KiB boundary
+            let len = data.len().min(16).min((0x1_0000 - (address & 0xffff)) as usize);
+            ihex_record(&mut out, 0, address as u16, &data[..len]);
+            address += len as u64;
+            data = &data[len..];
+        }
+    }
+    ihex_record(&mut out, 5, 0, &(entry as u32).to_be_bytes());
+    ihex_record(&mut out, 1, 0, &[]);
+    out
+}
+
+/// The contents of one memory region, from the lowest address loaded into it
+pub struct Region {
+    pub name: &'static str,
+    pub start: u64,
+    pub data: Vec<u8>,
+}
+
+/// Gathers the loadable segments of `elf` into the memory regions they are loaded into, filling
+/// the gaps between segments with zeros.
+pub fn regions(elf: &elf::Elf) -> Result<Vec<Region>, DynError> {
+    if let Some(s) = elf.segments.iter().find(|s| {
+        !s.data.is_empty() && !MEMORY.iter().any(|&(_, base, len)| s.paddr >= base && s.paddr < base + len)
+    }) {
+        return Err(format!("segment at {:#x} is outside every memory region", s.paddr).into());
+    }
+    let mut regions = Vec::new();
+    for &(name, base, len) in MEMORY.iter() {
+        let in_region = |s: &&elf::Segment| s.paddr >= base && s.paddr < base + len;
+        let (start, data) = match elf::flatten(elf.segments.iter().filter(in_region)) {
+            Some(image) => image,
+            None => continue,
+        };
+        if start + data.len() as u64 > base + len {
+            return Err(format!(
+                "segment at {:#x} runs past the end of {} at {:#x}",
+                start,
+                name,
+                base + len
+            )
+            .into());
+        }
+        regions.push(Region { name, start, data });
+    }
+    Ok(regions)
+}
+
+/// Writes each of `formats` next to the binary at `bin`, built from the ELF at `elf`.
+pub fn write(formats: &[Format], elf: &Path, bin: &Path) -> Result<(), DynError> {
+    if formats.iter().all(|&f| f == Format::Bin) {
+        return Ok(());
+    }
+    let elf = elf::parse(&std::fs::read(elf)?)?;
+    let regions = regions(&elf)?;
+    let dir = bin.parent().map(Path::to_path_buf).unwrap_or_default();
+    for &format in formats {
+        match format {
+            Format::Bin => {}
+            Format::Readmemh(bits) => {
+                let path = dir.join("rv32.mem");
+                std::fs::write(&path, readmemh(&std::fs::read(bin)?, bits))?;
+                println!("{}-bit $readmemh file created at {}", bits, path.display());
+            }
+            Format::Ihex => {
+                let path = dir.join("rv32.hex");
+                std::fs::write(&path, ihex(&regions, elf.entry))?;
+                println!("Intel HEX file created at {}", path.display());
+            }
+            Format::Split => {
+                for region in regions.iter() {
+                    let path = dir.join(format!("rv32-{}.bin", region.name));
+                    std::fs::write(&path, &region.data)?;
+                    println!(
+                        "{} image created at {}, loading at {:#x}",
+                        region.name,
+                        path.display(),
+                        region.start
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The bytes of one Intel HEX record, checking that they sum to zero
+    fn record(line: &str) -> Vec<u8> {
+        let hex = line.strip_prefix(':').unwrap();
+        let bytes: Vec<u8> =
+            (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect();
+        assert_eq!(bytes[0] as usize + 5, bytes.len(), "record length of {}", line);
+        assert_eq!(bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)), 0, "checksum of {}", line);
+        bytes
+    }
+
+    #[test]
+    fn ihex_checksums() {
+        let mut out = String::new();
+        // the example record from the Intel HEX specification
+        let data =
+            [0x21, 0x46, 0x01, 0x36, 0x01, 0x21, 0x47, 0x01, 0x36, 0x00, 0x7e, 0xfe, 0x09, 0xd2, 0x19, 0x01];
+        ihex_record(&mut out, 0, 0x0100, &data);
+        assert_eq!(out, ":10010000214601360121470136007EFE09D2190140\n");
+
+        let data: Vec<u8> = (0..=255).collect();
+        let hex = ihex(&[Region { name: "flash", start: RRAM_BASE, data }], RRAM_BASE);
+        for line in hex.lines() {
+            record(line);
+        }
+    }
+
+    #[test]
+    fn ihex_upper_address() {
+        let regions = [
+            Region { name: "flash", start: RRAM_BASE, data: vec![0xaa; 4] },
+            Region { name: "ram", start: 0x6100_fff8, data: (0..24).collect() },
+        ];
+        let hex = ihex(&regions, RRAM_BASE);
+        let lines: Vec<&str> = hex.lines().collect();
+        assert_eq!(lines[0], ":0200000460009A");
+        assert_eq!(lines[1], ":04000000AAAAAAAA54");
+        // the 24 bytes at 0x6100_fff8 are split at the 64 KiB boundary, each part under its own base
+        assert_eq!(lines[2], ":02000004610099");
+        assert_eq!(record(lines[3])[..4], [8, 0xff, 0xf8, 0]);
+        assert_eq!(lines[4], ":02000004610198");
+        let data = record(lines[5]);
+        assert_eq!(data[..4], [16, 0, 0, 0]);
+        assert_eq!(data[4..20], (8..24).collect::<Vec<u8>>()[..]);
+        assert_eq!(lines.len(), 8);
+    }
+
+    #[test]
+    fn ihex_entry_and_eof() {
+        let hex = ihex(&[Region { name: "flash", start: RRAM_BASE, data: vec![0; 16] }], 0x6000_0100);
+        let lines: Vec<&str> = hex.lines().collect();
+        assert_eq!(lines[lines.len() - 2], ":040000056000010096");
+        assert_eq!(lines[lines.len() - 1], ":00000001FF");
+    }
+
+    #[test]
+    fn readmemh_words() {
+        let image = [0x01, 0x02, 0x03, 0x04, 0x05];
+        let mem = readmemh(&image, 32);
+        let words: Vec<&str> = mem.lines().skip(1).collect();
+        assert!(mem.starts_with("// 5 bytes from 0x60000000, 32-bit words\n"));
+        assert_eq!(words, ["04030201", "00000005"]);
+
+        let image: Vec<u8> = (1..=33).collect();
+        let mem = readmemh(&image, 256);
+        let words: Vec<&str> = mem.lines().skip(1).collect();
+        assert_eq!(words.len(), 2);
+        let first: String = (1..=32).rev().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(words[0], first);
+        assert_eq!(words[1], format!("{}21", "0".repeat(62)));
+    }
+}
@@ -4,10 +4,26 @@ use std::{
     process::Command,
 };
 
+mod elf;
+mod features;
+mod formats;
+mod results;
+mod size;
+mod tracebuf;
+mod wtest;
+
 type DynError = Box<dyn std::error::Error>;
 
 const TARGET: &str = "riscv32imac-unknown-none-elf";
 
+/// Base of RRAM, where the boot image is loaded
+const RRAM_BASE: u64 = 0x6000_0000;
+/// Size of RRAM; `FLASH` in `tests/link.x`
+const RRAM_LEN: u64 = 0x40_0000;
+/// Default offset of the CM7 firmware in a simulation image; `__ROM_BASE` in
+/// `cm7/gcc_daric_mbox.ld`
+const CM7_OFFSET: u64 = 0x30_0000;
+
 #[derive(Debug)]
 #[allow(dead_code)]
 enum BuildError {
@@ -36,23 +52,86 @@ fn try_main() -> Result<(), DynError> {
     let hw_pkgs = ["tests"];
     let mut args = env::args();
     let task = args.nth(1);
-    let features = get_flag("--feature")?;
+    let features = || features::resolve(&get_flag("--feature")?, &get_flag("--preset")?);
     match task.as_deref() {
-        Some("boot-image") => build_hw_image(false, features, &hw_pkgs)?,
+        Some("boot-image") => {
+            let formats = formats::parse(&get_flag("--format")?, &get_flag("--word-bits")?)?;
+            let image = build_hw_image(false, features()?, &hw_pkgs)?;
+            formats::write(&formats, &image.elf, &image.bin)?;
+        }
+        Some("sim-image") => build_sim_image(
+            features()?,
+            &hw_pkgs,
+            get_flag("--cm7")?,
+            get_flag("--cm7-offset")?,
+            get_flag("--out")?,
+        )?,
+        Some("size") => {
+            let elf = match file_arg(&mut args, &["--feature", "--preset", "--cm7-offset", "--symbols"]) {
+                Some(elf) => PathBuf::from(elf),
+                None => {
+                    build(&hw_pkgs, false, Some(TARGET), Some("tests".into()), features()?)?.join("tests")
+                }
+            };
+            let cm7_offset = match get_flag("--cm7-offset")?.last() {
+                Some(offset) => parse_u64(offset)?,
+                None => CM7_OFFSET,
+            };
+            let symbols = match get_flag("--symbols")?.last() {
+                Some(n) => parse_u64(n)? as usize,
+                None => 5,
+            };
+            size::size(&elf, cm7_offset, symbols)?
+        }
+        Some("results") => results::results(file_arg(&mut args, &["--dump-base"]), get_flag("--dump-base")?)?,
+        Some("tracebuf") => {
+            tracebuf::tracebuf(file_arg(&mut args, &["--dump-base"]), get_flag("--dump-base")?)?
+        }
+        Some("wtest") => wtest::wtest(file_arg(&mut args, &[]))?,
         _ => print_help(),
     }
     Ok(())
 }
 
+/// The first of `args` that is neither a flag nor the value of one of the `flags` that take one
+fn file_arg(args: &mut impl Iterator<Item = String>, flags: &[&str]) -> Option<String> {
+    let mut value = false;
+    args.find(|a| {
+        let file = !value && !a.starts_with('-');
+        value = flags.contains(&a.as_str());
+        file
+    })
+}
+
 fn print_help() {
     eprintln!(
         "Tasks:
-boot-image     builds a boot image
+boot-image     builds a boot image, plus any extra formats
+               [--preset <name>] [--feature <a,b>] [--no-default-features]
+               [--format readmemh|ihex|split] [--word-bits 32|64|128|256]
+sim-image      builds a boot image with the CM7 firmware appended, for simulation
+               --cm7 <bin> [--cm7-offset <offset>] [--out <file>] [--preset <name>] [--feature <a,b>]
+size           reports the section sizes and largest symbols of the RV32 image, and checks that it
+               stays clear of the CM7 firmware and the reserved RRAM at the top
+               [elf] [--preset <name>] [--feature <a,b>] [--cm7-offset <offset>] [--symbols <n>]
+results        parses a test run's result record from a console log or SRAM dump
+               [file] [--dump-base <addr>]
+tracebuf       recovers the console output from the trace buffer in an SRAM dump
+               [file] [--dump-base <addr>]
+wtest          decodes the events in a simulator trace of csrtest WTEST writes
+               [file]
 "
     )
 }
 
-fn build_hw_image(debug: bool, features: Vec<String>, packages: &[&str]) -> Result<(), DynError> {
+/// The outputs of `build_hw_image()`
+struct HwImage {
+    elf: PathBuf,
+    bin: PathBuf,
+}
+
+/// Builds the RV32 image.
+fn build_hw_image(debug: bool, features: Vec<String>, packages: &[&str]) -> Result<HwImage, DynError> {
     // make the ELF file
     let mut boot = build(packages, debug, Some(TARGET), Some("tests".into()), features)?;
 
@@ -61,47 +140,110 @@ fn build_hw_image(debug: bool, features: Vec<String>, packages: &[&str]) -> Resu
     let mut boot_bin = project_root();
     boot_bin.push("rv32.bin");
 
-    // dump the ELF file
-    let listing = std::fs::File::create("rv32.map")?;
-    let status = Command::new(objdump())
+    // the listing is optional, so that the image can be built with just rustup
+    match Command::new(objdump())
         .current_dir(project_root())
-        .args(&["-d", "-S", boot.as_os_str().to_str().unwrap()])
-        .stdout(std::process::Stdio::from(listing))
-        .status()?;
-    if !status.success() {
-        return Err("cargo build failed".into());
+        .args(["-d", "-S", boot.as_os_str().to_str().unwrap()])
+        .output()
+    {
+        Ok(output) if output.status.success() => std::fs::write("rv32.map", output.stdout)?,
+        Ok(output) => println!(
+            "warning: {} failed, so there is no rv32.map listing: {}",
+            objdump(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(e) => println!(
+            "note: {} is not available ({}), so there is no rv32.map listing; set OBJDUMP to use \
+             another objdump",
+            objdump(),
+            e
+        ),
     }
 
-    // output the binary
-    let status = Command::new(objcopy())
-        .current_dir(project_root())
-        .args(&[
-            "-S",
-            "-O",
-            "binary",
-            boot.as_os_str().to_str().unwrap(),
-            boot_bin.as_os_str().to_str().unwrap(),
-        ])
-        .status()?;
-    if !status.success() {
-        return Err("cargo build failed".into());
+    let bin = hw_bin(&elf::parse(&std::fs::read(&boot)?)?)?;
+    std::fs::write(&boot_bin, &bin)?;
+    println!();
+    println!("Bootloader binary file created at {}", boot_bin.as_os_str().to_str().unwrap());
+
+    Ok(HwImage { elf: boot, bin: boot_bin })
+}
+
+/// The binary to load into RRAM from `elf`, padded to multiple of 32 bytes length
+fn hw_bin(elf: &elf::Elf) -> Result<Vec<u8>, DynError> {
+    let (start, mut bin) = elf.flat_image().ok_or("the ELF file has nothing to load")?;
+    if start != RRAM_BASE || bin.len() as u64 > RRAM_LEN {
+        return Err(format!(
+            "the image loads at {:#x}..{:#x}, outside RRAM; check tests/link.x",
+            start,
+            start + bin.len() as u64
+        )
+        .into());
     }
+    let padding_needed = (32 - (bin.len() % 32)) % 32;
+    bin.resize(bin.len() + padding_needed, 0);
+    Ok(bin)
+}
 
-    // Pad to multiple of 32 bytes length
-    let mut file = std::fs::OpenOptions::new().read(true).write(true).open(boot_bin)?;
-    use std::io::{Seek, Write};
-    // Get the current size of the file
-    let current_size = file.seek(std::io::SeekFrom::End(0))?;
-    let padding_needed = (32 - (current_size % 32)) % 32;
+/// Builds the RV32 image and places the CM7 firmware after it at `cm7_offset` in RRAM, so that
+/// both can be preloaded into the simulation with the mailbox tests.
+fn build_sim_image(
+    features: Vec<String>,
+    packages: &[&str],
+    cm7: Vec<String>,
+    cm7_offset: Vec<String>,
+    out: Vec<String>,
+) -> Result<(), DynError> {
+    let cm7_file = cm7.last().ok_or("sim-image: expected --cm7 <bin>")?;
+    let cm7_offset = match cm7_offset.last() {
+        Some(offset) => parse_u64(offset)?,
+        None => CM7_OFFSET,
+    };
+    let out = match out.last() {
+        Some(out) => PathBuf::from(out),
+        None => project_root().join("boot.bin"),
+    };
+    // read the CM7 firmware first, so that a bad path doesn't cost a build
+    let cm7 = std::fs::read(cm7_file)?;
+    let rv32 = std::fs::read(build_hw_image(false, features, packages)?.bin)?;
 
-    // Pad the file with zeros if needed
-    if padding_needed > 0 {
-        let padding = vec![0u8; padding_needed as usize];
-        file.write_all(&padding)?;
+    let rv32_end = rv32.len() as u64;
+    let cm7_end = cm7_offset + cm7.len() as u64;
+    if rv32_end > cm7_offset {
+        return Err(format!(
+            "sim-image: the RV32 image ends at {:#x}, past the CM7 firmware at {:#x}",
+            RRAM_BASE + rv32_end,
+            RRAM_BASE + cm7_offset
+        )
+        .into());
+    }
+    if cm7_end > RRAM_LEN {
+        return Err(format!(
+            "sim-image: the CM7 firmware ends at {:#x}, past the end of RRAM at {:#x}",
+            RRAM_BASE + cm7_end,
+            RRAM_BASE + RRAM_LEN
+        )
+        .into());
     }
-    println!();
-    println!("Bootloader binary file created at {}", boot.as_os_str().to_str().unwrap());
 
+    let mut image = rv32;
+    image.resize(cm7_offset as usize, 0);
+    image.extend_from_slice(&cm7);
+    std::fs::write(&out, &image)?;
+
+    println!();
+    println!("Simulation image created at {}", out.display());
+    println!("    {:<8} {:>12} {:>12} {:>10}", "region", "start", "end", "size");
+    for (name, start, end) in
+        [("rv32", 0, rv32_end), ("free", rv32_end, cm7_offset), ("cm7", cm7_offset, cm7_end)]
+    {
+        println!(
+            "    {:<8} {:>#12x} {:>#12x} {:>#10x}",
+            name,
+            RRAM_BASE + start,
+            RRAM_BASE + end,
+            end - start
+        );
+    }
     Ok(())
 }
 
@@ -116,9 +258,9 @@ fn build(
     let mut args = vec!["build"];
     print!("Building");
 
-    args.push("--no-default-features");
-    args.push("--features");
-    args.push("aes-zkn");
+    if env::args().any(|a| a == "--no-default-features") {
+        args.push("--no-default-features");
+    }
 
     for package in packages {
         print!(" {}", package);
@@ -137,11 +279,9 @@ fn build(
         args.push("--release");
     }
 
-    if features.len() > 0 {
-        for feature in features.iter() {
-            args.push("--features");
-            args.push(feature);
-        }
+    for feature in features.iter() {
+        args.push("--features");
+        args.push(feature);
     }
 
     let mut dir = project_root();
@@ -167,13 +307,21 @@ fn build(
 
 fn cargo() -> String { env::var("CARGO").unwrap_or_else(|_| "cargo".to_string()) }
 
-fn objcopy() -> String { env::var("OBJCOPY").unwrap_or_else(|_| "riscv-none-elf-objcopy".to_string()) }
 fn objdump() -> String { env::var("OBJDUMP").unwrap_or_else(|_| "riscv-none-elf-objdump".to_string()) }
 
 fn project_root() -> PathBuf {
     Path::new(&env!("CARGO_MANIFEST_DIR")).ancestors().nth(1).unwrap().to_path_buf()
 }
 
+/// Parses a decimal or `0x`-prefixed hex number, allowing `_` separators
+fn parse_u64(s: &str) -> Result<u64, DynError> {
+    let s = s.replace('_', "");
+    Ok(match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16)?,
+        None => s.parse()?,
+    })
+}
+
 fn get_flag(flag: &str) -> Result<Vec<String>, DynError> {
     let mut list = Vec::<String>::new();
     let args = env::args();
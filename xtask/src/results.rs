@@ -0,0 +1,308 @@
+//! Host-side parser for the result record emitted by `tests/src/results.rs`.
+//!
+//! Accepts either a console log containing the `@@` lines, or a raw memory dump that contains
+//! the SRAM results mailbox.
+
+use crate::DynError;
+
+/// Address of the results mailbox in SRAM; must match `tests/src/results.rs`
+pub const RESULTS_ADDR: u64 = 0x6110_1000;
+const RESULTS_MAGIC: u32 = 0x7e57_2e5d;
+const RESULTS_VERSION: u32 = 1;
+const HEADER_WORDS: usize = 4;
+const NAME_BYTES: usize = 16;
+/// Outcome tags indexed by `SuiteOutcome::code()`
+const OUTCOMES: [&str; 6] = ["notrun", "pass", "fail", "skip", "xfail", "xpass"];
+
+#[derive(Debug, Default)]
+pub struct SuiteResult {
+    pub id: u32,
+    pub name: String,
+    /// One of `OUTCOMES`
+    pub outcome: String,
+    pub total: u32,
+    pub passed: u32,
+    pub skipped: u32,
+    pub expected_failures: u32,
+    /// Case IDs of failed cases, with names when the source carries them
+    pub failed: Vec<(u32, Option<String>)>,
+    /// Failed case count, which can exceed `failed.len()` if the suite ran out of case slots
+    pub failed_count: u32,
+    pub elapsed_ticks: u64,
+    pub cycles: u64,
+}
+
+impl SuiteResult {
+    /// Skipped and expected-fail suites don't fail the run
+    pub fn is_failure(&self) -> bool { self.outcome == "fail" }
+}
+
+/// Parses the `@@` lines out of a console log. Any other output is ignored.
+pub fn parse_log(log: &str) -> Result<Vec<SuiteResult>, DynError> {
+    let mut suites = Vec::new();
+    let mut expected = None;
+    for line in log.lines() {
+        // the record may share a line with a simulator timestamp or similar prefix
+        let line = match line.find("@@") {
+            Some(pos) => line[pos..].trim_end(),
+            None => continue,
+        };
+        let fields: Vec<&str> = line.split('|').collect();
+        match fields[0] {
+            "@@RESULT" => {
+                if fields.len() != 3 {
+                    return Err(format!("malformed record header: {}", line).into());
+                }
+                if fields[1].parse::<u32>()? != RESULTS_VERSION {
+                    return Err(format!("unsupported record version {}", fields[1]).into());
+                }
+                // a log can hold several runs; only the last one counts
+                suites.clear();
+                expected = Some(fields[2].parse::<usize>()?);
+            }
+            "@@SUITE" => {
+                if fields.len() != 11 {
+                    return Err(format!("malformed suite record: {}", line).into());
+                }
+                let mut failed = Vec::new();
+                for id in fields[8].split(',').filter(|s| !s.is_empty()) {
+                    let (id, name) = match id.split_once(':') {
+                        Some((id, name)) => (id, Some(name.to_owned())),
+                        None => (id, None),
+                    };
+                    failed.push((id.parse()?, name));
+                }
+                suites.push(SuiteResult {
+                    id: fields[1].parse()?,
+                    name: fields[2].to_owned(),
+                    outcome: fields[3].to_owned(),
+                    total: fields[4].parse()?,
+                    passed: fields[5].parse()?,
+                    skipped: fields[6].parse()?,
+                    expected_failures: fields[7].parse()?,
+                    failed_count: failed.len() as u32,
+                    failed,
+                    elapsed_ticks: fields[9].parse()?,
+                    cycles: fields[10].parse()?,
+                });
+            }
+            "@@END" => {
+                if Some(suites.len()) != expected {
+                    return Err(format!(
+                        "record lists {} suites but {} were found",
+                        expected.unwrap_or(0),
+                        suites.len()
+                    )
+                    .into());
+                }
+                return Ok(suites);
+            }
+            _ => (),
+        }
+    }
+    Err("no complete result record found (missing @@RESULT or @@END line)".into())
+}
+
+/// Parses the results mailbox out of a raw memory dump. If `dump_base` is given, it is the
+/// address of the first byte of the dump; otherwise the dump is searched for the mailbox magic.
+pub fn parse_dump(dump: &[u8], dump_base: Option<u64>) -> Result<Vec<SuiteResult>, DynError> {
+    let word = |offset: usize| -> Option<u32> {
+        dump.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    let start = match dump_base {
+        Some(base) => {
+            if base > RESULTS_ADDR || (RESULTS_ADDR - base) as usize >= dump.len() {
+                return Err(
+                    format!("dump at {:#x} does not cover the mailbox at {:#x}", base, RESULTS_ADDR).into()
+                );
+            }
+            let start = (RESULTS_ADDR - base) as usize;
+            if word(start) != Some(RESULTS_MAGIC) {
+                return Err("mailbox magic not found; the run did not complete".into());
+            }
+            start
+        }
+        None => (0..dump.len())
+            .step_by(4)
+            .find(|&o| word(o) == Some(RESULTS_MAGIC) && word(o + 4) == Some(RESULTS_VERSION))
+            .ok_or("mailbox magic not found in dump")?,
+    };
+
+    let version = word(start + 4).ok_or("truncated mailbox header")?;
+    if version != RESULTS_VERSION {
+        return Err(format!("unsupported mailbox version {}", version).into());
+    }
+    let count = word(start + 8).ok_or("truncated mailbox header")? as usize;
+    let record_words = word(start + 12).ok_or("truncated mailbox header")? as usize;
+    if record_words < 12 + NAME_BYTES / 4 {
+        return Err(format!("mailbox record size of {} words is too small", record_words).into());
+    }
+
+    let mut suites = Vec::new();
+    for n in 0..count {
+        let base = start + (HEADER_WORDS + n * record_words) * 4;
+        let w = |i: usize| word(base + i * 4).ok_or("truncated mailbox record");
+        let failed_mask = w(7)?;
+        let name_bytes = dump.get(base + 48..base + 48 + NAME_BYTES).ok_or("truncated mailbox record")?;
+        let name_len = name_bytes.iter().position(|&b| b == 0).unwrap_or(NAME_BYTES);
+        suites.push(SuiteResult {
+            id: w(0)?,
+            name: String::from_utf8_lossy(&name_bytes[..name_len]).into_owned(),
+            outcome: OUTCOMES.get(w(1)? as usize).unwrap_or(&"unknown").to_string(),
+            total: w(2)?,
+            passed: w(3)?,
+            skipped: w(4)?,
+            expected_failures: w(5)?,
+            failed_count: w(6)?,
+            failed: (0..32).filter(|b| failed_mask & (1 << b) != 0).map(|b| (b, None)).collect(),
+            elapsed_ticks: w(8)? as u64 | (w(9)? as u64) << 32,
+            cycles: w(10)? as u64 | (w(11)? as u64) << 32,
+        });
+    }
+    Ok(suites)
+}
+
+/// Prints a summary table. Returns the number of failed suites; skipped and expected-fail suites
+/// are not counted.
+pub fn print_table(suites: &[SuiteResult]) -> usize {
+    println!(
+        "{:>3} {:<16} {:<7} {:>6} {:>6} {:>6} {:>6} {:>6} {:>10} {:>12}  failed cases",
+        "id", "suite", "outcome", "total", "pass", "skip", "xfail", "fail", "ticks", "cycles"
+    );
+    let mut failures = 0;
+    for s in suites {
+        if s.is_failure() {
+            failures += 1;
+        }
+        let failed: Vec<String> = s
+            .failed
+            .iter()
+            .map(|(id, name)| match name {
+                Some(name) => format!("{}:{}", id, name),
+                None => format!("{}", id),
+            })
+            .collect();
+        let row = format!(
+            "{:>3} {:<16} {:<7} {:>6} {:>6} {:>6} {:>6} {:>6} {:>10} {:>12}  {}",
+            s.id,
+            s.name,
+            s.outcome,
+            s.total,
+            s.passed,
+            s.skipped,
+            s.expected_failures,
+            s.failed_count,
+            s.elapsed_ticks,
+            s.cycles,
+            failed.join(",")
+        );
+        println!("{}", row.trim_end());
+    }
+    println!("{} of {} suites failed", failures, suites.len());
+    failures
+}
+
+/// `cargo xtask results <file> [--dump-base <addr>]`
+pub fn results(file: Option<String>, dump_base: Vec<String>) -> Result<(), DynError> {
+    let file = file.ok_or("results: expected a log or memory dump file")?;
+    let data = std::fs::read(&file)?;
+    let dump_base = match dump_base.last() {
+        Some(base) => Some(crate::parse_u64(base)?),
+        None => None,
+    };
+    // a console log is treated as text; anything else is a memory dump
+    let suites = match (dump_base, std::str::from_utf8(&data)) {
+        (None, Ok(text)) if text.contains("@@RESULT|") => parse_log(text)?,
+        _ => parse_dump(&data, dump_base)?,
+    };
+    if print_table(&suites) != 0 {
+        return Err("test run had failures".into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG: &str = "\
+booting... 001
+Test RESET VALUE: 3/3 passing
+@@RESULT|1|2
+@@SUITE|0|RESET VALUE|pass|3|3|0|0||120|45000
+[    1234 ns] @@SUITE|13|RRAM|xfail|12|10|0|2|4:corner_mid,7:end_cap|30012|7340032000
+@@END|0
+";
+
+    /// A mailbox of `records`, each `(id, outcome, name)` with fixed counts, after `pad` bytes
+    fn dump(pad: usize, records: &[(u32, u32, &str)]) -> Vec<u8> {
+        let mut words = vec![RESULTS_MAGIC, RESULTS_VERSION, records.len() as u32, 16];
+        for &(id, outcome, name) in records {
+            // total, passed, skipped, xfail, failed count, failed mask, elapsed, cycles
+            words.extend_from_slice(&[id, outcome, 8, 6, 1, 0, 1, 0b100, 500, 0, 0x2345_6789, 1]);
+            let mut bytes = [0u8; NAME_BYTES];
+            bytes[..name.len()].copy_from_slice(name.as_bytes());
+            words.extend(bytes.chunks(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])));
+        }
+        let mut dump = vec![0xffu8; pad];
+        dump.extend(words.iter().flat_map(|w| w.to_le_bytes()));
+        dump
+    }
+
+    #[test]
+    fn log() {
+        let suites = parse_log(LOG).unwrap();
+        assert_eq!(suites.len(), 2);
+        assert_eq!(
+            (suites[0].id, suites[0].name.as_str(), suites[0].outcome.as_str()),
+            (0, "RESET VALUE", "pass")
+        );
+        assert!(suites[0].failed.is_empty());
+        let rram = &suites[1];
+        assert_eq!((rram.id, rram.name.as_str(), rram.outcome.as_str()), (13, "RRAM", "xfail"));
+        assert_eq!((rram.total, rram.passed, rram.skipped, rram.expected_failures), (12, 10, 0, 2));
+        assert_eq!(rram.failed, [(4, Some("corner_mid".to_string())), (7, Some("end_cap".to_string()))]);
+        assert_eq!((rram.failed_count, rram.elapsed_ticks, rram.cycles), (2, 30012, 7_340_032_000));
+        assert!(!rram.is_failure());
+    }
+
+    #[test]
+    fn log_suite_count_mismatch() {
+        let log = LOG.replace("@@RESULT|1|2", "@@RESULT|1|3");
+        let err = parse_log(&log).unwrap_err().to_string();
+        assert_eq!(err, "record lists 3 suites but 2 were found");
+        assert!(parse_log(&LOG.replace("@@END|0\n", "")).is_err());
+    }
+
+    #[test]
+    fn dump_searched() {
+        let suites = parse_dump(&dump(0x40, &[(1, 1, "SATP Setup"), (13, 2, "RRAM")]), None).unwrap();
+        assert_eq!(suites.len(), 2);
+        assert_eq!(
+            (suites[0].id, suites[0].name.as_str(), suites[0].outcome.as_str()),
+            (1, "SATP Setup", "pass")
+        );
+        let rram = &suites[1];
+        assert_eq!((rram.id, rram.name.as_str(), rram.outcome.as_str()), (13, "RRAM", "fail"));
+        assert_eq!((rram.total, rram.passed, rram.skipped, rram.expected_failures), (8, 6, 1, 0));
+        assert_eq!((rram.failed_count, rram.failed.clone()), (1, vec![(2, None)]));
+        assert_eq!((rram.elapsed_ticks, rram.cycles), (500, 0x1_2345_6789));
+    }
+
+    #[test]
+    fn dump_at_base() {
+        // a dump of SRAM from 0x6110_0000, with the mailbox 0x1000 bytes in
+        let dump = dump(0x1000, &[(5, 3, "SIXTEEN_BYTES_XX")]);
+        let suites = parse_dump(&dump, Some(RESULTS_ADDR - 0x1000)).unwrap();
+        assert_eq!((suites[0].name.as_str(), suites[0].outcome.as_str()), ("SIXTEEN_BYTES_XX", "skip"));
+        assert!(parse_dump(&dump, Some(RESULTS_ADDR - 0x800)).is_err());
+        assert!(parse_dump(&dump, Some(RESULTS_ADDR + 4)).is_err());
+    }
+
+    #[test]
+    fn dump_truncated() {
+        let mut dump = dump(0, &[(1, 1, "SATP Setup")]);
+        dump.truncate(dump.len() - 4);
+        assert_eq!(parse_dump(&dump, None).unwrap_err().to_string(), "truncated mailbox record");
+    }
+}
@@ -0,0 +1,283 @@
+//! `xtask size`: the section sizes and largest symbols of the RV32 image, and a check that the
+//! image stays clear of the RRAM set aside for the CM7 firmware and for keys, data and access
+//! control.
+
+use std::path::Path;
+
+use crate::{DynError, RRAM_BASE, RRAM_LEN, elf};
+
+/// `__ROM_SIZE` in `cm7/gcc_daric_mbox.ld`
+const CM7_LEN: u64 = 0x8_0000;
+
+/// RRAM reserved by `tests/src/rram.rs`, as `(name, start, end)`
+const RESERVED: [(&str, u64, u64); 5] = [
+    ("oneway", 0x603D_A000, 0x603D_B000),
+    ("oneway2", 0x603D_B000, 0x603D_C000),
+    ("acram", 0x603D_C000, 0x603E_0000),
+    ("datasel", 0x603E_0000, 0x603F_0000),
+    ("keysel", 0x603F_0000, RRAM_BASE + RRAM_LEN),
+];
+
+/// The symbols of one module, with their total size
+struct Module {
+    name: String,
+    total: u64,
+    symbols: Vec<(String, u64)>,
+}
+
+/// Undoes the escapes in one component of a legacy Rust symbol name, like `$LT$` for `<` and `..`
+/// for `::`.
+fn unescape(part: &str) -> String {
+    // a leading `_` only keeps the component from starting with `$`
+    let mut rest = if part.starts_with("_$") { &part[1..] } else { part };
+    let mut out = String::new();
+    while let Some(c) = rest.chars().next() {
+        if let Some(r) = rest.strip_prefix("..") {
+            out.push_str("::");
+            rest = r;
+            continue;
+        }
+        if c == '$' {
+            if let Some(end) = rest[1..].find('$') {
+                let code = &rest[1..end + 1];
+                let decoded = match code {
+                    "SP" => Some('@'),
+                    "BP" => Some('*'),
+                    "RF" => Some('&'),
+                    "LT" => Some('<'),
+                    "GT" => Some('>'),
+                    "LP" => Some('('),
+                    "RP" => Some(')'),
+                    "C" => Some(','),
+                    _ => code
+                        .strip_prefix('u')
+                        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                        .and_then(char::from_u32),
+                };
+                if let Some(decoded) = decoded {
+                    out.push(decoded);
+                    rest = &rest[end + 2..];
+                    continue;
+                }
+            }
+        }
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+/// Demangles a legacy Rust symbol name (`_ZN...E`), leaving off the hash. Other names are returned
+/// as they are.
+fn demangle(name: &str) -> String {
+    let mut rest = match name.strip_prefix("_ZN") {
+        Some(rest) => rest,
+        None => return name.to_string(),
+    };
+    let mut parts = Vec::new();
+    loop {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let len = match rest[..digits].parse::<usize>() {
+            Ok(len) => len,
+            Err(_) => break,
+        };
+        match rest.get(digits..digits + len) {
+            Some(part) => parts.push(part),
+            None => return name.to_string(),
+        }
+        rest = &rest[digits + len..];
+    }
+    if rest != "E" || parts.is_empty() {
+        return name.to_string();
+    }
+    if let Some(hash) = parts.last() {
+        if hash.len() == 17 && hash.starts_with('h') && hash[1..].chars().all(|c| c.is_ascii_hexdigit()) {
+            parts.pop();
+        }
+    }
+    parts.iter().map(|p| unescape(p)).collect::<Vec<_>>().join("::")
+}
+
+/// The module a demangled symbol belongs to, to the depth of `crate::module`. Methods in
+/// `<Type as Trait>` impls go with the type, or with the trait if the type isn't a path.
+fn module(name: &str) -> String {
+    let mut path = name;
+    if let Some(inner) = name.strip_prefix('<') {
+        let (ty, tr) = inner.split_once(" as ").unwrap_or((inner, ""));
+        path = if ty.contains("::") || tr.is_empty() { ty } else { tr };
+    }
+    let path = path.trim_start_matches(['&', '*', '[']).trim_start_matches("mut ");
+    // the path up to the first generic argument, without the item itself
+    let path = path.split(['<', '>', ' ']).next().unwrap_or("");
+    let mut parts: Vec<&str> = path.split("::").collect();
+    parts.pop();
+    if parts.is_empty() || parts[0].is_empty() {
+        return "(global)".to_string();
+    }
+    parts.truncate(2);
+    parts.join("::")
+}
+
+/// Prints the report for the ELF file at `path`, showing the `symbols` largest symbols of each
+/// module. Fails if the loaded image overlaps the CM7 firmware at `cm7_offset` in RRAM or a
+/// reserved region.
+pub fn size(path: &Path, cm7_offset: u64, symbols: usize) -> Result<(), DynError> {
+    println!("{}", path.display());
+    report(&elf::parse(&std::fs::read(path)?)?, cm7_offset, symbols)
+}
+
+/// The body of `size()`, for an ELF file that has been read in
+fn report(elf: &elf::Elf, cm7_offset: u64, symbols: usize) -> Result<(), DynError> {
+    let in_rram = |s: &&elf::Segment| s.paddr >= RRAM_BASE && s.paddr < RRAM_BASE + RRAM_LEN;
+    let (start, image) = elf::flatten(elf.segments.iter().filter(in_rram))
+        .ok_or("size: the ELF file loads nothing into RRAM")?;
+    let end = start + image.len() as u64;
+
+    println!();
+    println!("    {:<24} {:>12} {:>12} {:>10}", "section", "address", "load", "size");
+    for section in elf.sections.iter() {
+        let load = match elf.load_addr(section) {
+            Some(load) => format!("{:#x}", load),
+            None => "-".to_string(),
+        };
+        println!("    {:<24} {:>#12x} {:>12} {:>#10x}", section.name, section.addr, load, section.size);
+    }
+
+    if elf.symbols.is_empty() {
+        println!();
+        println!("note: the ELF file has no symbol table, so there are no symbol sizes");
+    } else {
+        // aliases share an address; count each once, under its most specific name
+        let mut syms: Vec<(String, &elf::Symbol)> =
+            elf.symbols.iter().map(|s| (demangle(&s.name), s)).collect();
+        syms.sort_by(|a, b| a.1.addr.cmp(&b.1.addr).then(b.0.contains("::").cmp(&a.0.contains("::"))));
+        syms.dedup_by(|a, b| a.1.addr == b.1.addr);
+
+        let mut modules: Vec<Module> = Vec::new();
+        for (name, sym) in syms {
+            let module = module(&name);
+            match modules.iter_mut().find(|m| m.name == module) {
+                Some(m) => {
+                    m.total += sym.size;
+                    m.symbols.push((name, sym.size));
+                }
+                None => {
+                    modules.push(Module { name: module, total: sym.size, symbols: vec![(name, sym.size)] })
+                }
+            }
+        }
+        modules.sort_by(|a, b| b.total.cmp(&a.total).then(a.name.cmp(&b.name)));
+        println!();
+        println!("Largest symbols by module:");
+        for mut module in modules {
+            module.symbols.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            println!("    {:<60} {:>#10x}", module.name, module.total);
+            for (name, size) in module.symbols.iter().take(symbols) {
+                println!("        {:<56} {:>#10x}", name, size);
+            }
+        }
+    }
+
+    let cm7 = ("cm7", RRAM_BASE + cm7_offset, RRAM_BASE + cm7_offset + CM7_LEN);
+    let mut reserved: Vec<(&str, u64, u64)> = core::iter::once(cm7).chain(RESERVED.iter().copied()).collect();
+    reserved.sort_by_key(|r| r.1);
+    let limit = reserved.iter().map(|r| r.1).filter(|&r| r >= start).min().unwrap_or(RRAM_BASE + RRAM_LEN);
+    println!();
+    println!(
+        "RRAM: the image takes {:#x}..{:#x} ({} bytes), leaving {} bytes before {:#x}",
+        start,
+        end,
+        image.len(),
+        limit.saturating_sub(end),
+        limit
+    );
+    println!("    {:<8} {:>12} {:>12} {:>10}", "region", "start", "end", "size");
+    let mut overlaps = Vec::new();
+    for &(name, r_start, r_end) in reserved.iter() {
+        let overlap = start < r_end && r_start < end;
+        println!(
+            "    {:<8} {:>#12x} {:>#12x} {:>#10x}{}",
+            name,
+            r_start,
+            r_end,
+            r_end - r_start,
+            if overlap { "  <-- overlaps the image" } else { "" }
+        );
+        if overlap {
+            overlaps.push(name);
+        }
+    }
+    if !overlaps.is_empty() {
+        return Err(format!("size: the image runs into reserved RRAM: {}", overlaps.join(", ")).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CM7_OFFSET;
+
+    #[test]
+    fn demangled_names() {
+        let names = [
+            ("_ZN4core3fmt5write17h0123456789abcdefE", "core::fmt::write", "core::fmt"),
+            (
+                "_ZN5tests7harness9run_timed17hfedcba9876543210E",
+                "tests::harness::run_timed",
+                "tests::harness",
+            ),
+            (
+                "_ZN4core3ptr44drop_in_place$LT$tests..harness..CaseLog$GT$17h5f0e2a9c1b3d4e6fE",
+                "core::ptr::drop_in_place<tests::harness::CaseLog>",
+                "core::ptr",
+            ),
+            (
+                "_ZN60_$LT$tests..harness..CaseLog$u20$as$u20$core..fmt..Debug$GT$3fmt17h0123456789abcdefE",
+                "<tests::harness::CaseLog as core::fmt::Debug>::fmt",
+                "tests::harness",
+            ),
+            // a trait impl for a type that isn't a path goes with the trait
+            (
+                "_ZN50_$LT$$u5b$u32$u5d$$u20$as$u20$core..fmt..Debug$GT$3fmt17h0123456789abcdefE",
+                "<[u32] as core::fmt::Debug>::fmt",
+                "core::fmt",
+            ),
+            // without a hash
+            ("_ZN5tests10rust_entryE", "tests::rust_entry", "tests"),
+            ("memcpy", "memcpy", "(global)"),
+            // not a complete mangled name
+            ("_ZN4core3fm", "_ZN4core3fm", "(global)"),
+        ];
+        for &(mangled, demangled, module_name) in names.iter() {
+            assert_eq!(demangle(mangled), demangled);
+            assert_eq!(module(demangled), module_name, "module of {}", demangled);
+        }
+    }
+
+    /// An ELF image loading `len` bytes at `paddr`, plus initialised data that runs from RAM
+    fn image(paddr: u64, len: usize) -> elf::Elf {
+        let segment =
+            |paddr, vaddr, len| elf::Segment { paddr, vaddr, memsz: len as u64, data: vec![0x13; len] };
+        elf::Elf {
+            entry: paddr,
+            segments: vec![segment(paddr, paddr, len), segment(0x6100_a000, 0x6100_a000, 0x100)],
+            sections: Vec::new(),
+            symbols: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn reserved_overlap() {
+        assert!(report(&image(RRAM_BASE, 0x1000), CM7_OFFSET, 5).is_ok());
+        // up to the CM7 firmware, and one byte into it
+        assert!(report(&image(RRAM_BASE, CM7_OFFSET as usize), CM7_OFFSET, 5).is_ok());
+        let err = report(&image(RRAM_BASE, CM7_OFFSET as usize + 1), CM7_OFFSET, 5).unwrap_err();
+        assert_eq!(err.to_string(), "size: the image runs into reserved RRAM: cm7");
+        // the CM7 firmware can be moved out of the way, but the reserved regions can't
+        assert!(report(&image(RRAM_BASE, CM7_OFFSET as usize + 1), 0x38_0000, 5).is_ok());
+        let err = report(&image(0x603D_9000, 0x2000), CM7_OFFSET, 5).unwrap_err();
+        assert_eq!(err.to_string(), "size: the image runs into reserved RRAM: oneway");
+        assert!(report(&image(0x6100_0000, 0x100), CM7_OFFSET, 5).is_err());
+    }
+}
@@ -0,0 +1,84 @@
+//! Host-side decoder for the console trace buffer written by `tests/src/tracebuf.rs`.
+//!
+//! Recovers the console output from a raw memory dump that contains the buffer, even if the
+//! DUART never produced any.
+
+use crate::DynError;
+
+/// Address of the buffer state in SRAM; must match `tests/src/tracebuf.rs`
+pub const TRACE_STATE: u64 = 0x6110_4000;
+/// Address of the buffer data in SRAM
+pub const TRACE_DATA: u64 = 0x6110_5000;
+const TRACE_MAGIC: u32 = 0x7ace_b0f0;
+const TRACE_VERSION: u32 = 1;
+
+/// Extracts the buffered text, oldest byte first. The second value is the number of bytes that
+/// were overwritten because the buffer wrapped.
+pub fn parse_dump(dump: &[u8], dump_base: Option<u64>) -> Result<(Vec<u8>, u64), DynError> {
+    let word = |offset: usize| -> Option<u32> {
+        dump.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    let start = match dump_base {
+        Some(base) => {
+            if base > TRACE_STATE || (TRACE_STATE - base) as usize >= dump.len() {
+                return Err(format!(
+                    "dump at {:#x} does not cover the trace buffer at {:#x}",
+                    base, TRACE_STATE
+                )
+                .into());
+            }
+            let start = (TRACE_STATE - base) as usize;
+            if word(start) != Some(TRACE_MAGIC) {
+                return Err("trace buffer magic not found; the firmware did not start".into());
+            }
+            start
+        }
+        None => (0..dump.len())
+            .step_by(4)
+            .find(|&o| word(o) == Some(TRACE_MAGIC) && word(o + 4) == Some(TRACE_VERSION))
+            .ok_or("trace buffer magic not found in dump")?,
+    };
+
+    let version = word(start + 4).ok_or("truncated trace buffer header")?;
+    if version != TRACE_VERSION {
+        return Err(format!("unsupported trace buffer version {}", version).into());
+    }
+    let len = word(start + 8).ok_or("truncated trace buffer header")? as usize;
+    let head = word(start + 12).ok_or("truncated trace buffer header")? as u64;
+    if len == 0 || !len.is_power_of_two() {
+        return Err(format!("bad trace buffer length {:#x}", len).into());
+    }
+    let data_start = start + (TRACE_DATA - TRACE_STATE) as usize;
+    let data = dump.get(data_start..data_start + len).ok_or("dump does not cover the trace buffer data")?;
+
+    // `head` counts every byte written; once it passes `len`, the oldest byte is the one at `head`
+    let (text, lost) = if head <= len as u64 {
+        (data[..head as usize].to_vec(), 0)
+    } else {
+        let split = head as usize & (len - 1);
+        let mut text = data[split..].to_vec();
+        text.extend_from_slice(&data[..split]);
+        (text, head - len as u64)
+    };
+    Ok((text, lost))
+}
+
+pub fn tracebuf(file: Option<String>, dump_base: Vec<String>) -> Result<(), DynError> {
+    let file = file.ok_or("tracebuf: expected a memory dump file")?;
+    let dump = std::fs::read(&file)?;
+    let dump_base = match dump_base.last() {
+        Some(base) => Some(crate::parse_u64(base)?),
+        None => None,
+    };
+    let (text, lost) = parse_dump(&dump, dump_base)?;
+    if lost != 0 {
+        println!("[... {} earlier bytes were overwritten]", lost);
+    }
+    // the firmware ends lines with a bare CR
+    let text = String::from_utf8_lossy(&text).replace("\r\n", "\n").replace('\r', "\n");
+    print!("{}", text);
+    if !text.ends_with('\n') {
+        println!();
+    }
+    Ok(())
+}
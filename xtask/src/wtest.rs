@@ -0,0 +1,380 @@
+//! Host-side decoder for the structured events in `tests/src/event.rs`.
+//!
+//! Reads a simulator trace of `csrtest::WTEST` writes and prints it as a timeline. Each line of
+//! the trace should end with one written word, either `0x`-prefixed or as exactly eight hex digits;
+//! if the line has more than one token, the first is taken as the timestamp. Other lines are
+//! ignored, and words that don't decode as events are shown raw, except for the end-of-run status
+//! from `tests/src/exit.rs`.
+
+use crate::DynError;
+
+const TAG: u32 = 0xE000_0000;
+const TAG_MASK: u32 = 0xF000_0000;
+/// Status word of the end-of-run protocol, followed by the failure count
+const EXIT_TAG: u32 = 0xc0de_5700;
+/// Indexed by `ExitStatus`
+const EXIT_STATUSES: [&str; 4] = ["pass", "fail", "panic", "timeout"];
+
+/// Indexed by the event kind
+const KINDS: [&str; 6] = ["begin", "end", "step", "pass", "fail", "value"];
+const KIND_STEP: u32 = 2;
+const KIND_PASS: u32 = 3;
+const KIND_FAIL: u32 = 4;
+const KIND_VALUE: u32 = 5;
+
+/// Indexed by `Subsystem`
+const SUBSYSTEMS: [&str; 16] = [
+    "?",
+    "harness",
+    "check",
+    "reset-value",
+    "irq-setup",
+    "irq",
+    "wfi",
+    "trap",
+    "satp",
+    "cache",
+    "ram",
+    "xip",
+    "rram",
+    "pio",
+    "init",
+    "console",
+];
+
+/// Named codes as `(subsystem, kind, code, name)`; mirrors the code modules in `event.rs`
+const CODES: &[(u32, u32, u32, &str)] = &[
+    (0x01, KIND_VALUE, 1, "selection"),
+    (0x02, KIND_FAIL, 1, "mismatch [addr, actual, expected]"),
+    (0x02, KIND_VALUE, 2, "mismatches"),
+    (0x03, KIND_VALUE, 1, "pc"),
+    (0x07, KIND_VALUE, 1, "scause"),
+    (0x07, KIND_VALUE, 2, "pending"),
+    (0x07, KIND_VALUE, 3, "[sepc, stval, sim]"),
+    (0x07, KIND_VALUE, 4, "sstatus"),
+    (0x07, KIND_STEP, 5, "pio irq"),
+    (0x08, KIND_VALUE, 1, "[asid, entry]"),
+    (0x09, KIND_VALUE, 1, "addr"),
+    (0x09, KIND_VALUE, 2, "read [bottom, top]"),
+    (0x09, KIND_VALUE, 3, "io read"),
+    (0x09, KIND_STEP, 0x20, "flush"),
+    (0x09, KIND_STEP, 0x21, "flushed"),
+    (0x09, KIND_STEP, 0x40, "io"),
+    (0x0a, KIND_STEP, 0x20, "flush"),
+    (0x0a, KIND_STEP, 0x21, "flushed"),
+    (0x0b, KIND_VALUE, 1, "result"),
+    (0x0c, KIND_VALUE, 1, "readback [addr, value]"),
+    (0x0c, KIND_VALUE, 2, "dma control"),
+    (0x0d, KIND_VALUE, 1, "tx"),
+    (0x0d, KIND_FAIL, 2, "mismatch [sent, received]"),
+    (0x0d, KIND_STEP, 0x10, "transfer"),
+    (0x0e, KIND_VALUE, 1, "uart div"),
+    (0x0f, KIND_VALUE, 1, "text"),
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Tag {
+    pub kind: u32,
+    pub subsystem: u32,
+    pub payload_len: usize,
+    pub code: u32,
+}
+
+pub fn decode_tag(word: u32) -> Option<Tag> {
+    if word & TAG_MASK != TAG {
+        return None;
+    }
+    let tag = Tag {
+        kind: (word >> 24) & 0xf,
+        subsystem: (word >> 16) & 0xff,
+        payload_len: ((word >> 12) & 0xf) as usize,
+        code: word & 0xfff,
+    };
+    if tag.kind as usize >= KINDS.len() || tag.subsystem == 0 || tag.subsystem as usize >= SUBSYSTEMS.len() {
+        return None;
+    }
+    Some(tag)
+}
+
+/// Describes the code of `tag`, by name where it has one.
+pub fn code_name(tag: &Tag) -> String {
+    if let Some((_, _, _, name)) =
+        CODES.iter().find(|&&(s, k, c, _)| s == tag.subsystem && k == tag.kind && c == tag.code)
+    {
+        return name.to_string();
+    }
+    match (tag.subsystem, tag.kind, tag.code) {
+        (_, 0, _) | (_, 1, _) => String::new(),
+        // offsets from the range bases in `event::trap` and `event::cache`
+        (0x07, KIND_VALUE, c) if c >= 0x100 => format!("irq bank {}", c - 0x100),
+        (0x09, KIND_STEP, c) if (0x10..0x20).contains(&c) => format!("pre-flush pass {}", c - 0x10),
+        (0x09, KIND_STEP, c) if (0x30..0x40).contains(&c) => format!("post-flush pass {}", c - 0x30),
+        (0x09, KIND_FAIL, c) | (0x0a, KIND_FAIL, c) if c >= 0xf00 => format!("config, test {}", c - 0xf00),
+        (_, KIND_PASS, c) | (_, KIND_FAIL, c) => format!("test {}", c),
+        (_, _, c) => format!("{}", c),
+    }
+}
+
+/// Pulls the timestamp and written word out of one trace line.
+fn parse_line(line: &str) -> Option<(Option<&str>, u32)> {
+    let tokens: Vec<&str> = line
+        .split(|c: char| c.is_whitespace() || c == ',' || c == ':' || c == '=')
+        .filter(|t| !t.is_empty())
+        .collect();
+    // only the last token, so that words like `add` or a cycle count elsewhere aren't taken
+    let last = tokens.last()?.replace('_', "");
+    let hex = match last.strip_prefix("0x").or_else(|| last.strip_prefix("0X")) {
+        Some(hex) => hex,
+        None if last.len() == 8 => last.as_str(),
+        None => return None,
+    };
+    let word = u32::from_str_radix(hex, 16).ok()?;
+    let time = if tokens.len() > 1 { Some(tokens[0]) } else { None };
+    Some((time, word))
+}
+
+fn emit(time: &Option<String>, tag: &Tag, payload: &[u32], out: &mut Vec<String>) {
+    let payload: Vec<String> = if (tag.subsystem, tag.kind, tag.code) == (0x0f, KIND_VALUE, 1) {
+        // console text, packed little-endian and padded with zeros
+        let bytes: Vec<u8> = payload.iter().flat_map(|w| w.to_le_bytes()).filter(|&b| b != 0).collect();
+        vec![format!("{:?}", String::from_utf8_lossy(&bytes))]
+    } else {
+        payload.iter().map(|w| format!("{:08x}", w)).collect()
+    };
+    let line = format!(
+        "{:>12} {:<12} {:<6} {:<32} {}",
+        time.as_deref().unwrap_or(""),
+        SUBSYSTEMS[tag.subsystem as usize],
+        KINDS[tag.kind as usize],
+        code_name(tag),
+        payload.join(" ")
+    );
+    out.push(line.trim_end().to_string());
+}
+
+/// Decodes a whole trace into timeline lines.
+pub fn decode(trace: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut pending: Option<(Option<String>, Tag, Vec<u32>)> = None;
+    let mut exit: Option<(Option<String>, u32)> = None;
+    for (time, word) in trace.lines().filter_map(parse_line) {
+        if let Some((t, status)) = exit.take() {
+            let status = EXIT_STATUSES.get(status as usize).copied().unwrap_or("unknown");
+            out.push(format!(
+                "{:>12} {:<12} {}, {} failed",
+                t.as_deref().unwrap_or(""),
+                "exit",
+                status,
+                word
+            ));
+            continue;
+        }
+        if let Some((t, tag, mut payload)) = pending.take() {
+            payload.push(word);
+            if payload.len() == tag.payload_len {
+                emit(&t, &tag, &payload, &mut out);
+            } else {
+                pending = Some((t, tag, payload));
+            }
+            continue;
+        }
+        if word & 0xffff_ff00 == EXIT_TAG {
+            exit = Some((time.map(String::from), word & 0xff));
+            continue;
+        }
+        match decode_tag(word) {
+            Some(tag) if tag.payload_len == 0 => emit(&time.map(String::from), &tag, &[], &mut out),
+            Some(tag) => pending = Some((time.map(String::from), tag, Vec::new())),
+            None => out.push(format!("{:>12} {:<12} {:08x}", time.unwrap_or(""), "raw", word)),
+        }
+    }
+    if let Some((t, tag, payload)) = pending {
+        emit(&t, &tag, &payload, &mut out);
+        out.push(format!(
+            "{:>12} {:<12} truncated: {} of {} payload words",
+            "",
+            "",
+            payload.len(),
+            tag.payload_len
+        ));
+    }
+    out
+}
+
+pub fn wtest(file: Option<String>) -> Result<(), DynError> {
+    let file = file.ok_or("wtest: expected a trace file")?;
+    let trace = std::fs::read_to_string(&file)?;
+    for line in decode(&trace) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `event::tag()`
+    fn tag(kind: u32, subsystem: u32, payload_len: usize, code: u32) -> u32 {
+        TAG | kind << 24 | subsystem << 16 | (payload_len as u32) << 12 | code
+    }
+
+    fn trace(words: &[u32]) -> String {
+        words.iter().enumerate().map(|(i, w)| format!("{} ns WTEST 0x{:08x}\n", i * 10, w)).collect()
+    }
+
+    #[test]
+    fn tag_round_trip() {
+        for kind in 0..KINDS.len() as u32 {
+            for subsystem in 1..SUBSYSTEMS.len() as u32 {
+                for &(payload_len, code) in [(0, 0), (3, 0x21), (15, 0xfff)].iter() {
+                    let word = tag(kind, subsystem, payload_len, code);
+                    assert_eq!(decode_tag(word), Some(Tag { kind, subsystem, payload_len, code }));
+                }
+            }
+        }
+        assert_eq!(decode_tag(tag(KIND_VALUE, 0, 0, 1)), None);
+        assert_eq!(decode_tag(tag(KIND_VALUE, SUBSYSTEMS.len() as u32, 0, 1)), None);
+        assert_eq!(decode_tag(tag(KINDS.len() as u32, 1, 0, 1)), None);
+        assert_eq!(decode_tag(0xc0de_600d), None);
+    }
+
+    #[test]
+    fn payload_grouping() {
+        let out = decode(&trace(&[
+            tag(KIND_FAIL, 0x02, 3, 1),
+            0x6000_0010,
+            0x1234_5678,
+            0x8765_4321,
+            tag(KIND_PASS, 0x0a, 0, 2),
+        ]));
+        assert_eq!(out.len(), 2);
+        assert!(out[0].contains("check") && out[0].contains("mismatch"));
+        assert!(out[0].ends_with("60000010 12345678 87654321"));
+        assert!(out[1].contains("ram") && out[1].contains("pass") && out[1].ends_with("test 2"));
+    }
+
+    #[test]
+    fn truncated_payload() {
+        let out = decode(&trace(&[tag(KIND_VALUE, 0x07, 3, 3), 0x8000_0000]));
+        assert_eq!(out.len(), 2);
+        assert!(out[0].ends_with("80000000"));
+        assert!(out[1].ends_with("truncated: 1 of 3 payload words"));
+    }
+
+    #[test]
+    fn exit_word() {
+        let out = decode(&trace(&[EXIT_TAG | 1, 3, 0xc0de_d02e]));
+        assert_eq!(out.len(), 2);
+        assert!(out[0].contains("exit") && out[0].ends_with("fail, 3 failed"));
+        assert!(out[1].contains("raw") && out[1].ends_with("c0ded02e"));
+    }
+
+    #[test]
+    fn simulator_lines() {
+        assert_eq!(
+            parse_line("  1234500 ns: tb.csrtest.WTEST = 0xe5010001"),
+            Some((Some("1234500"), 0xe501_0001))
+        );
+        assert_eq!(parse_line("1234500 WTEST e5010001"), Some((Some("1234500"), 0xe501_0001)));
+        assert_eq!(parse_line("0xe5010001"), Some((None, 0xe501_0001)));
+        assert_eq!(parse_line("0xdead_beef"), Some((None, 0xdead_beef)));
+        // words that happen to be hex, and decimal counts, aren't written words
+        assert_eq!(parse_line("1234500 ns: add a0, a0, 0xe5010001 be cafe"), None);
+        assert_eq!(parse_line("1234500 ns: WTEST written, cycle 1234567"), None);
+        assert_eq!(parse_line(""), None);
+    }
+
+    /// The names declared in `enum name { ... }` in `source`, with their values if given
+    fn enum_variants<'a>(source: &'a str, name: &str) -> Vec<(&'a str, Option<u32>)> {
+        let start = source.find(&format!("pub enum {} {{", name)).expect("enum not found");
+        let body = &source[start..];
+        let body = &body[body.find('{').unwrap() + 1..body.find('}').unwrap()];
+        body.lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with("//"))
+            .map(|l| {
+                let l = l.trim_end_matches(',');
+                let (name, value) = l.split_once('=').unwrap_or((l, ""));
+                let name = name.split('(').next().unwrap().trim();
+                let value = value.trim().strip_prefix("0x").map(|v| u32::from_str_radix(v, 16).unwrap());
+                (name, value)
+            })
+            .collect()
+    }
+
+    fn kebab(name: &str) -> String {
+        let mut out = String::new();
+        for (i, c) in name.chars().enumerate() {
+            if c.is_ascii_uppercase() && i > 0 {
+                out.push('-');
+            }
+            out.push(c.to_ascii_lowercase());
+        }
+        out
+    }
+
+    #[test]
+    fn tables_match_event_rs() {
+        let source = std::fs::read_to_string(crate::project_root().join("tests/src/event.rs")).unwrap();
+
+        let kinds: Vec<String> = enum_variants(&source, "Event").iter().map(|(n, _)| kebab(n)).collect();
+        assert_eq!(kinds, KINDS);
+
+        let subsystems = enum_variants(&source, "Subsystem");
+        assert_eq!(subsystems.len() + 1, SUBSYSTEMS.len());
+        for (name, value) in subsystems {
+            assert_eq!(SUBSYSTEMS[value.unwrap() as usize], kebab(name));
+        }
+
+        // every constant in a code module has a name for the first subsystem its doc line gives, and
+        // every entry in CODES is declared for one of the subsystems its module is for
+        let mut subsystems: Vec<u32> = Vec::new();
+        let mut kind = None;
+        let mut declared = Vec::new();
+        for line in source.lines() {
+            let line = line.trim();
+            if line.starts_with("/// `Subsystem::") {
+                subsystems = line
+                    .split("`Subsystem::")
+                    .skip(1)
+                    .map(|s| {
+                        let name = s.split('`').next().unwrap();
+                        let n = SUBSYSTEMS.iter().position(|&s| s == kebab(name));
+                        n.unwrap_or_else(|| panic!("unknown subsystem in `{}`", line)) as u32
+                    })
+                    .collect();
+            } else if let Some(doc) = line.strip_prefix("/// ") {
+                if let Some((k, _)) = doc.split_once(':') {
+                    if let Some(k) = KINDS.iter().position(|&n| n.eq_ignore_ascii_case(k)) {
+                        kind = Some(k as u32);
+                    }
+                }
+            } else if let Some(decl) = line.strip_prefix("pub const ") {
+                // the tag layout constants come before the code modules
+                let subsystem = match subsystems.first() {
+                    Some(&s) => s,
+                    None => continue,
+                };
+                let (name, value) = decl.split_once(": u16 = ").unwrap();
+                let value = value.trim_end_matches(';');
+                let code = match value.strip_prefix("0x") {
+                    Some(hex) => u32::from_str_radix(hex, 16).unwrap(),
+                    None => value.parse().unwrap(),
+                };
+                let kind = kind.take().unwrap_or_else(|| panic!("`{}` has no kind in its doc", name));
+                let described = code_name(&Tag { kind, subsystem, payload_len: 0, code });
+                assert!(
+                    described != code.to_string() && described != format!("test {}", code),
+                    "{}::{} has no name in CODES",
+                    SUBSYSTEMS[subsystem as usize],
+                    name
+                );
+                declared.extend(subsystems.iter().map(|&s| (s, kind, code)));
+            }
+        }
+        for &(s, k, c, name) in CODES {
+            assert!(declared.contains(&(s, k, c)), "`{}` in CODES is not declared in event.rs", name);
+        }
+    }
+}